@@ -0,0 +1,441 @@
+use crate::config::deserialize_key;
+use crate::error::Error;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "geoip")]
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::Resolver;
+
+// -------------------------------------------------------------------------------------------------
+// GlobalAddress
+// -------------------------------------------------------------------------------------------------
+
+/// Global address information
+#[derive(Debug)]
+pub struct GlobalAddress {
+    /// Address checking time
+    pub time: DateTime<Utc>,
+    /// Access latency
+    pub latency: Duration,
+    /// Global IP address by IPv4
+    pub v4addr: Option<Ipv4Addr>,
+    /// Global IP address by IPv6
+    pub v6addr: Option<Ipv6Addr>,
+    /// Provider name
+    pub provider: String,
+    /// Hostname by reverse DNS ( set by `lookup_hostname` )
+    pub hostname: Option<String>,
+    /// AS information ( set by `lookup_asn` )
+    pub asn: Option<AsnInfo>,
+    /// Geolocation information ( set by providers which have `geo` )
+    pub geo: Option<GeoInfo>,
+    /// Raw response which the address was parsed from ( set by providers which have `capture_response` )
+    pub raw_response: Option<String>,
+    /// Privacy extension state of IPv6 address ( set by `lookup_ipv6_privacy` )
+    pub ipv6_privacy: Option<Ipv6Privacy>,
+}
+
+impl GlobalAddress {
+    pub fn from_v4(addr: Ipv4Addr, provider: &str, latency: Duration) -> Self {
+        GlobalAddress {
+            time: Utc::now(),
+            latency,
+            v4addr: Some(addr),
+            v6addr: None,
+            provider: String::from(provider),
+            hostname: None,
+            asn: None,
+            geo: None,
+            raw_response: None,
+            ipv6_privacy: None,
+        }
+    }
+
+    pub fn from_v6(addr: Ipv6Addr, provider: &str, latency: Duration) -> Self {
+        GlobalAddress {
+            time: Utc::now(),
+            latency,
+            v4addr: None,
+            v6addr: Some(addr),
+            provider: String::from(provider),
+            hostname: None,
+            asn: None,
+            geo: None,
+            raw_response: None,
+            ipv6_privacy: None,
+        }
+    }
+
+    /// Global IP address of either IPv4 or IPv6
+    pub fn addr(&self) -> Option<IpAddr> {
+        self.v4addr
+            .map(IpAddr::V4)
+            .or_else(|| self.v6addr.map(IpAddr::V6))
+    }
+
+    /// Check whether IPv6 address is a temporary address of local interface, and store it to `ipv6_privacy`
+    ///
+    /// It is `None` if the address is not found in local interfaces ( e.g. behind NAT ) or on non-Linux.
+    pub fn lookup_ipv6_privacy(&mut self) -> Result<Option<&Ipv6Privacy>, Error> {
+        self.ipv6_privacy = match self.v6addr {
+            Some(addr) => ipv6_privacy(addr, &local_ipv6()?),
+            None => None,
+        };
+        Ok(self.ipv6_privacy.as_ref())
+    }
+
+    /// Replace temporary IPv6 address by the stable address found by `lookup_ipv6_privacy`,
+    /// and return whether it is replaced
+    pub fn prefer_stable(&mut self) -> bool {
+        match self.ipv6_privacy {
+            Some(Ipv6Privacy {
+                temporary: true,
+                stable: Some(stable),
+            }) => {
+                self.v6addr = Some(stable);
+                self.ipv6_privacy = Some(Ipv6Privacy {
+                    temporary: false,
+                    stable: Some(stable),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lookup hostname of the address by reverse DNS ( PTR ), and store it to `hostname`
+    pub fn lookup_hostname(&mut self) -> Result<Option<&str>, Error> {
+        let addr = match self.addr() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let resolver = Resolver::new(ResolverConfig::default(), ResolverOpts::default())?;
+        self.hostname = match resolver.reverse_lookup(addr) {
+            Ok(names) => names
+                .iter()
+                .next()
+                .map(|x| x.to_utf8().trim_end_matches('.').to_string()),
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(self.hostname.as_deref())
+    }
+
+    /// Lookup AS information of the address by Team Cymru IP to ASN mapping, and store it to `asn`
+    pub fn lookup_asn(&mut self) -> Result<Option<&AsnInfo>, Error> {
+        let addr = match self.addr() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let resolver = Resolver::new(ResolverConfig::default(), ResolverOpts::default())?;
+
+        let origin = match lookup_txt(&resolver, &asn_origin_name(&addr))? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let mut asn = AsnInfo::parse_origin(&origin)?;
+        if let Some(x) = lookup_txt(&resolver, &format!("AS{}.asn.cymru.com.", asn.asn))? {
+            asn.organization = AsnInfo::parse_organization(&x)?;
+        }
+        self.asn = Some(asn);
+        Ok(self.asn.as_ref())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// GeoInfo
+// -------------------------------------------------------------------------------------------------
+
+/// Geolocation information of global address
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoInfo {
+    /// Country
+    pub country: Option<String>,
+    /// Region
+    pub region: Option<String>,
+    /// City
+    pub city: Option<String>,
+    /// Organization
+    pub organization: Option<String>,
+}
+
+/// Keys of geolocation information in JSON response
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeoKeys {
+    /// Key of country
+    #[serde(default, deserialize_with = "deserialize_key")]
+    pub country: Vec<String>,
+    /// Key of region
+    #[serde(default, deserialize_with = "deserialize_key")]
+    pub region: Vec<String>,
+    /// Key of city
+    #[serde(default, deserialize_with = "deserialize_key")]
+    pub city: Vec<String>,
+    /// Key of organization
+    #[serde(default, deserialize_with = "deserialize_key")]
+    pub organization: Vec<String>,
+}
+
+// -------------------------------------------------------------------------------------------------
+// AsnInfo
+// -------------------------------------------------------------------------------------------------
+
+/// AS information of global address
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsnInfo {
+    /// AS number
+    pub asn: u32,
+    /// Announced prefix including the address
+    pub prefix: String,
+    /// Country code
+    pub country: String,
+    /// Regional internet registry
+    pub registry: String,
+    /// Holder organization of AS
+    pub organization: String,
+}
+
+impl AsnInfo {
+    /// Parse origin record ( e.g. "13335 | 1.1.1.0/24 | AU | apnic | 2011-08-11" )
+    pub(crate) fn parse_origin(record: &str) -> Result<Self, Error> {
+        let err = || Error::AsnParseFailed {
+            record: String::from(record),
+        };
+        let fields: Vec<_> = record.split('|').map(|x| x.trim()).collect();
+        if fields.len() < 4 {
+            return Err(err());
+        }
+        // multiple origin AS are separated by space
+        let asn = fields[0]
+            .split_whitespace()
+            .next()
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(err)?;
+        Ok(AsnInfo {
+            asn,
+            prefix: String::from(fields[1]),
+            country: String::from(fields[2]),
+            registry: String::from(fields[3]),
+            organization: String::new(),
+        })
+    }
+
+    /// Parse AS description record ( e.g. "13335 | US | arin | 2010-07-14 | CLOUDFLARENET - Cloudflare, Inc., US" )
+    pub(crate) fn parse_organization(record: &str) -> Result<String, Error> {
+        record
+            .split('|')
+            .nth(4)
+            .map(|x| String::from(x.trim()))
+            .ok_or_else(|| Error::AsnParseFailed {
+                record: String::from(record),
+            })
+    }
+}
+
+/// Query name of Team Cymru origin record
+pub(crate) fn asn_origin_name(addr: &IpAddr) -> String {
+    match addr {
+        IpAddr::V4(addr) => {
+            let o = addr.octets();
+            format!("{}.{}.{}.{}.origin.asn.cymru.com.", o[3], o[2], o[1], o[0])
+        }
+        IpAddr::V6(addr) => {
+            let mut name = String::new();
+            for o in addr.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", o & 0xf, o >> 4));
+            }
+            name.push_str("origin6.asn.cymru.com.");
+            name
+        }
+    }
+}
+
+/// Lookup the first TXT record, or `None` if not found
+fn lookup_txt(resolver: &Resolver, name: &str) -> Result<Option<String>, Error> {
+    match resolver.txt_lookup(name) {
+        Ok(txt) => Ok(txt.iter().next().map(|x| {
+            x.txt_data()
+                .iter()
+                .map(|x| String::from_utf8_lossy(x))
+                .collect::<String>()
+        })),
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Ipv6Privacy
+// -------------------------------------------------------------------------------------------------
+
+/// Privacy extension state of IPv6 global address on the local interface
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ipv6Privacy {
+    /// Whether the address is a temporary address of privacy extension ( RFC 4941 )
+    pub temporary: bool,
+    /// Stable address in the same prefix of the same interface
+    pub stable: Option<Ipv6Addr>,
+}
+
+/// IPv6 address of local interface
+#[derive(Clone, Debug)]
+pub(crate) struct LocalIpv6 {
+    addr: Ipv6Addr,
+    interface: String,
+    prefix_len: u8,
+    flags: u8,
+}
+
+// flags of /proc/net/if_inet6
+const IFA_F_TEMPORARY: u8 = 0x01;
+const IFA_F_DEPRECATED: u8 = 0x20;
+const IFA_F_TENTATIVE: u8 = 0x40;
+
+/// Parse /proc/net/if_inet6, and return global addresses
+pub(crate) fn parse_if_inet6(text: &str) -> Vec<LocalIpv6> {
+    text.lines()
+        .filter_map(|line| {
+            let cols: Vec<_> = line.split_whitespace().collect();
+            if cols.len() < 6 || cols[3] != "00" {
+                return None;
+            }
+            Some(LocalIpv6 {
+                addr: Ipv6Addr::from(u128::from_str_radix(cols[0], 16).ok()?),
+                interface: String::from(cols[5]),
+                prefix_len: u8::from_str_radix(cols[2], 16).ok()?,
+                flags: u8::from_str_radix(cols[4], 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Global IPv6 addresses of local interfaces, which are available on Linux only
+fn local_ipv6() -> Result<Vec<LocalIpv6>, Error> {
+    if cfg!(target_os = "linux") {
+        Ok(parse_if_inet6(&std::fs::read_to_string(
+            "/proc/net/if_inet6",
+        )?))
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Privacy extension state of `addr`, or `None` if it is not a local address
+pub(crate) fn ipv6_privacy(addr: Ipv6Addr, locals: &[LocalIpv6]) -> Option<Ipv6Privacy> {
+    let local = locals.iter().find(|x| x.addr == addr)?;
+    let mask = u128::MAX
+        .checked_shl(128 - u32::from(local.prefix_len.min(128)))
+        .unwrap_or(0);
+    let stable = locals
+        .iter()
+        .find(|x| {
+            x.interface == local.interface
+                && x.flags & (IFA_F_TEMPORARY | IFA_F_DEPRECATED | IFA_F_TENTATIVE) == 0
+                && u128::from(x.addr) & mask == u128::from(addr) & mask
+        })
+        .map(|x| x.addr);
+    Some(Ipv6Privacy {
+        temporary: local.flags & IFA_F_TEMPORARY != 0,
+        stable,
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+// GeoIP
+// -------------------------------------------------------------------------------------------------
+
+/// Paths of local MaxMind databases ( e.g. GeoLite2 )
+#[cfg(feature = "geoip")]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeoIpConfig {
+    /// Path of City or Country database
+    pub country: Option<std::path::PathBuf>,
+    /// Path of ASN database
+    pub asn: Option<std::path::PathBuf>,
+}
+
+#[cfg(feature = "geoip")]
+impl GlobalAddress {
+    /// Lookup geolocation and AS information of the address from local MaxMind databases,
+    /// and store them to `geo` and `asn`
+    pub fn lookup_geoip(&mut self, config: &GeoIpConfig) -> Result<(), Error> {
+        use maxminddb::{geoip2, MaxMindDBError, Reader};
+
+        let addr = match self.addr() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let name = |names: Option<BTreeMap<&str, &str>>| {
+            names.and_then(|x| x.get("en").map(|x| String::from(*x)))
+        };
+
+        if let Some(ref path) = config.country {
+            let reader = Reader::open_readfile(path)?;
+            match reader.lookup::<geoip2::City>(addr) {
+                Ok(city) => {
+                    let geo = self.geo.get_or_insert_with(GeoInfo::default);
+                    geo.country = city.country.and_then(|x| x.iso_code).map(String::from);
+                    geo.region = city
+                        .subdivisions
+                        .and_then(|x| x.into_iter().next())
+                        .and_then(|x| name(x.names));
+                    geo.city = city.city.and_then(|x| name(x.names));
+                }
+                Err(MaxMindDBError::AddressNotFoundError(_)) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if let Some(ref path) = config.asn {
+            let reader = Reader::open_readfile(path)?;
+            match reader.lookup_prefix::<geoip2::Asn>(addr) {
+                Ok((asn, prefix_len)) => {
+                    let organization = asn
+                        .autonomous_system_organization
+                        .map(String::from)
+                        .unwrap_or_default();
+                    if let Some(ref mut geo) = self.geo {
+                        geo.organization = Some(organization.clone());
+                    }
+                    self.asn = Some(AsnInfo {
+                        asn: asn.autonomous_system_number.unwrap_or(0),
+                        prefix: fmt_prefix(&addr, prefix_len),
+                        country: self
+                            .geo
+                            .as_ref()
+                            .and_then(|x| x.country.clone())
+                            .unwrap_or_default(),
+                        registry: String::new(),
+                        organization,
+                    });
+                }
+                Err(MaxMindDBError::AddressNotFoundError(_)) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format network prefix including the address
+#[cfg(feature = "geoip")]
+pub(crate) fn fmt_prefix(addr: &IpAddr, len: usize) -> String {
+    match addr {
+        IpAddr::V4(x) => {
+            let mask = u32::MAX.checked_shl(32 - len.min(32) as u32).unwrap_or(0);
+            format!("{}/{}", Ipv4Addr::from(u32::from(*x) & mask), len)
+        }
+        IpAddr::V6(x) => {
+            let mask = u128::MAX
+                .checked_shl(128 - len.min(128) as u32)
+                .unwrap_or(0);
+            format!("{}/{}", Ipv6Addr::from(u128::from(*x) & mask), len)
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::net::IpAddr;
+use std::time::Duration;
+use thiserror::Error;
+
+// -------------------------------------------------------------------------------------------------
+// Error
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    AddrParse(#[from] std::net::AddrParseError),
+    #[error(transparent)]
+    JsonParse(#[from] serde_json::Error),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Dns(#[from] trust_dns_resolver::error::ResolveError),
+    #[error(transparent)]
+    DnsProto(#[from] trust_dns_resolver::proto::error::ProtoError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+    #[cfg(feature = "geoip")]
+    #[error(transparent)]
+    GeoIp(#[from] maxminddb::MaxMindDBError),
+    #[error("all providers failed to get address{}", fmt_errors(.errors))]
+    AllProvidersFailed { errors: Vec<(String, Error)> },
+    #[error("failed to connect ({url})")]
+    ConnectionFailed {
+        url: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    #[error("failed by timeout to {url} ({}ms)", .timeout.as_millis())]
+    Timeout { url: String, timeout: Duration },
+    #[error("failed to parse address ({addr})")]
+    AddrParseFailed { addr: String },
+    #[error("failed to parse dns string ({url})")]
+    DnsParseFailed { url: String },
+    #[error("failed to find padding ({padding})")]
+    PaddingMismatch { padding: String },
+    #[error("failed to find key ({key})")]
+    KeyNotFound { key: String },
+    #[error("regex is not specified ({url})")]
+    RegexNotSpecified { url: String },
+    #[error("failed to match regex ({regex})")]
+    RegexMismatch { regex: String },
+    #[error("invalid dns record ({record})")]
+    DnsRecordInvalid { record: String },
+    #[error("lookup is cancelled")]
+    Cancelled,
+    #[error("environment variable not found ({name})")]
+    EnvVarNotFound { name: String },
+    #[error("providers returned different addresses ({})", fmt_answers(.answers))]
+    AddressMismatch { answers: Vec<(String, IpAddr)> },
+    #[error("failed to find WAN connection service ({url})")]
+    UpnpServiceNotFound { url: String },
+    #[error("failed to parse ASN record ({record})")]
+    AsnParseFailed { record: String },
+    #[error("unknown protocol ({protocol})")]
+    UnknownProtocol { protocol: String },
+    #[error("address is not global ({addr})")]
+    NotGlobal { addr: IpAddr },
+    #[error("deadline exceeded ({}ms)", .deadline.as_millis())]
+    DeadlineExceeded { deadline: Duration },
+    #[error("{path}: {message}")]
+    ConfigInvalid { path: String, message: String },
+    #[error("rate limited for {}s ({url})", .retry_after.as_secs())]
+    RateLimited { url: String, retry_after: Duration },
+    #[error("skipped by rate limit ({}s left)", .remaining.as_secs())]
+    Backoff { remaining: Duration },
+    #[error("redirected to {location} ({url})")]
+    Redirected { url: String, location: String },
+    #[error("response is larger than {limit} bytes ({url})")]
+    ResponseTooLarge { url: String, limit: usize },
+    #[error("checksum mismatch of {url} (expected: {expected}, actual: {actual})")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Category of [`Error`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Connection or name resolution failed
+    Network,
+    /// Timeout or deadline exceeded
+    Timeout,
+    /// Unexpected response from provider
+    Parse,
+    /// Invalid configuration
+    Config,
+    /// Lookup is cancelled
+    Cancelled,
+}
+
+impl Error {
+    /// Returns the category of the error.
+    ///
+    /// `AllProvidersFailed` takes the category shared by all providers, or `Network` if they differ.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ConnectionFailed { .. }
+            | Error::Dns(_)
+            | Error::DnsProto(_)
+            | Error::UpnpServiceNotFound { .. }
+            | Error::Redirected { .. }
+            | Error::RateLimited { .. }
+            | Error::Backoff { .. } => ErrorKind::Network,
+            Error::Io(e) => match e.kind() {
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => ErrorKind::Timeout,
+                _ => ErrorKind::Network,
+            },
+            Error::Timeout { .. } | Error::DeadlineExceeded { .. } => ErrorKind::Timeout,
+            Error::AddrParse(_)
+            | Error::JsonParse(_)
+            | Error::AddrParseFailed { .. }
+            | Error::PaddingMismatch { .. }
+            | Error::KeyNotFound { .. }
+            | Error::RegexMismatch { .. }
+            | Error::AddressMismatch { .. }
+            | Error::AsnParseFailed { .. }
+            | Error::NotGlobal { .. }
+            | Error::ResponseTooLarge { .. } => ErrorKind::Parse,
+            Error::Toml(_)
+            | Error::Regex(_)
+            | Error::DnsParseFailed { .. }
+            | Error::RegexNotSpecified { .. }
+            | Error::DnsRecordInvalid { .. }
+            | Error::EnvVarNotFound { .. }
+            | Error::UnknownProtocol { .. }
+            | Error::ConfigInvalid { .. }
+            | Error::ChecksumMismatch { .. } => ErrorKind::Config,
+            #[cfg(feature = "geoip")]
+            Error::GeoIp(_) => ErrorKind::Config,
+            Error::Cancelled => ErrorKind::Cancelled,
+            Error::AllProvidersFailed { errors } => {
+                let mut kinds = errors.iter().map(|(_, e)| e.kind());
+                match kinds.next() {
+                    Some(first) if kinds.all(|x| x == first) => first,
+                    _ => ErrorKind::Network,
+                }
+            }
+        }
+    }
+
+    /// Returns true if the error is caused by timeout
+    pub fn is_timeout(&self) -> bool {
+        self.kind() == ErrorKind::Timeout
+    }
+
+    /// Returns true if the error is caused by network, including timeout
+    pub fn is_network(&self) -> bool {
+        matches!(self.kind(), ErrorKind::Network | ErrorKind::Timeout)
+    }
+
+    /// Returns true if the response from provider can't be parsed
+    pub fn is_parse(&self) -> bool {
+        self.kind() == ErrorKind::Parse
+    }
+
+    /// Returns true if the configuration is invalid
+    pub fn is_config(&self) -> bool {
+        self.kind() == ErrorKind::Config
+    }
+}
+
+fn fmt_errors(errors: &[(String, Error)]) -> String {
+    errors
+        .iter()
+        .map(|(name, err)| format!("\n    {}: {}", name, fmt_chain(err)))
+        .collect()
+}
+
+/// Format the error followed by its sources
+pub(crate) fn fmt_chain(err: &Error) -> String {
+    let mut ret = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(x) = source {
+        ret.push_str(&format!(": {}", x));
+        source = x.source();
+    }
+    ret
+}
+
+fn fmt_answers(answers: &[(String, IpAddr)]) -> String {
+    answers
+        .iter()
+        .map(|(name, addr)| format!("{}: {}", name, addr))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
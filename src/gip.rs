@@ -1,8 +1,18 @@
 use anyhow::{Context, Error};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, SecondsFormat, Utc};
 use dirs::home_dir;
-use gip::{Provider, ProviderAny, ProviderInfoType};
+use gip::{
+    Attempt, CancellationToken, GeoInfo, Order, Provider, ProviderAny, ProviderInfo,
+    ProviderInfoList, ProviderInfoProtocol, ProviderInfoType, ProviderStats, Strategy,
+};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Read;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use structopt::{clap, StructOpt};
 
 // -------------------------------------------------------------------------------------------------
@@ -25,6 +35,14 @@ pub struct Opt {
     #[structopt(short = "6", long = "v6", conflicts_with = "v4")]
     pub v6: bool,
 
+    /// Try the address family ( "v4" or "v6" ) first, and fall back to the other if it fails
+    #[structopt(
+        long = "prefer",
+        parse(try_from_str = parse_family),
+        conflicts_with_all = &["v4", "v6"]
+    )]
+    pub prefer: Option<ProviderInfoType>,
+
     /// Show by plane text ( default )
     #[structopt(
         short = "p",
@@ -52,33 +70,297 @@ pub struct Opt {
     )]
     pub show_json: bool,
 
-    /// Timeout per each provider by milliseconds
-    #[structopt(long = "timeout", default_value = "1000")]
-    pub timeout: usize,
+    /// Timeout per each provider by milliseconds ( default: 1000 )
+    #[structopt(long = "timeout")]
+    pub timeout: Option<usize>,
+
+    /// Connect timeout of HTTP providers by milliseconds
+    #[structopt(long = "connect-timeout")]
+    pub connect_timeout: Option<usize>,
+
+    /// Read timeout by milliseconds, which limits the whole request of HTTP providers
+    #[structopt(long = "read-timeout")]
+    pub read_timeout: Option<usize>,
 
     /// Key string of JSON format
     #[structopt(long = "json-key", default_value = "ip")]
     pub json_key: String,
 
-    /// Proxy for HTTP access ( "host:port" )
+    /// Format of check time in verbose message by strftime ( e.g. "%Y-%m-%dT%H:%M:%S%:z" )
+    #[structopt(long = "time-format")]
+    pub time_format: Option<TimeFormat>,
+
+    /// Proxy for HTTP, TCP and DNS access ( "host:port" or "scheme://[user:pass@]host:port" )
     #[structopt(long = "proxy")]
     pub proxy: Option<String>,
 
-    /// Show provider list
+    /// User-Agent for HTTP access
+    #[structopt(long = "user-agent")]
+    pub user_agent: Option<String>,
+
+    /// Source address of outgoing connections
+    #[structopt(long = "source-ip", conflicts_with = "interface")]
+    pub source_ip: Option<IpAddr>,
+
+    /// Use the address of the network interface as source address ( e.g. "eth1" )
+    #[structopt(long = "interface")]
+    pub interface: Option<String>,
+
+    /// Path of provider configuration ( "-" for stdin, default: $HOME/.gip.toml )
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Directory of the remote provider list, rate limits and statistics ( default: $XDG_CACHE_HOME/gip )
+    #[structopt(long = "cache-dir", env = "GIP_CACHE_DIR", parse(from_os_str))]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Query the URL instead of configured providers
+    #[structopt(long = "url")]
+    pub url: Option<String>,
+
+    /// Protocol of --url ( "plane" (default), "json", "regex", "header", "key-value", "tcp", "dns" )
+    #[structopt(long = "protocol", requires = "url")]
+    pub protocol: Option<ProviderInfoProtocol>,
+
+    /// Key of --url ( can be specified multiple times for nested JSON )
+    #[structopt(long = "key", number_of_values = 1, requires = "url")]
+    pub key: Vec<String>,
+
+    /// Use only the provider of the name ( can be specified multiple times )
+    #[structopt(long = "provider", number_of_values = 1)]
+    pub provider: Vec<String>,
+
+    /// Don't use the provider of the name ( can be specified multiple times )
+    #[structopt(long = "exclude", number_of_values = 1)]
+    pub exclude: Vec<String>,
+
+    /// Use only the providers of the protocol ( "http", "dns", "tcp", "upnp", or a protocol of --protocol )
+    #[structopt(long = "only-protocol", number_of_values = 1)]
+    pub only_protocol: Vec<ProtocolFilter>,
+
+    /// Query all providers and fail if they return different addresses
+    #[structopt(long = "consensus")]
+    pub consensus: bool,
+
+    /// Query at most N providers concurrently by --consensus, check and bench
+    #[structopt(long = "parallel", value_name = "N")]
+    pub parallel: Option<NonZeroUsize>,
+
+    /// Reject non-global addresses ( private, loopback, link-local, etc. )
+    #[structopt(long = "require-global")]
+    pub require_global: bool,
+
+    /// Show the raw response of the provider in verbose message
+    #[structopt(long = "raw-response", requires = "verbose")]
+    pub raw_response: bool,
+
+    /// Use only providers which don't send the query in cleartext ( HTTPS )
+    #[structopt(long = "secure-only")]
+    pub secure_only: bool,
+
+    /// Lookup hostname of the address by reverse DNS ( shown by --verbose )
+    #[structopt(long = "ptr")]
+    pub ptr: bool,
+
+    /// Show the stable address instead if the IPv6 address is a temporary address of this host
+    #[structopt(long = "prefer-stable")]
+    pub prefer_stable: bool,
+
+    /// Lookup AS number, organization and prefix of the address
+    #[structopt(long = "asn")]
+    pub asn: bool,
+
+    /// Show geolocation of the address by providers which support it
+    #[structopt(long = "geo")]
+    pub geo: bool,
+
+    /// Check whether the address is a Tor exit through the proxy ( default: socks5h://127.0.0.1:9050 )
+    #[structopt(long = "check-tor")]
+    pub check_tor: bool,
+
+    /// Show provider list with protocol and URL ( by JSON with --json )
     #[structopt(short = "l", long = "list")]
     pub show_list: bool,
 
     /// Show verbose message
     #[structopt(short = "v", long = "verbose")]
     pub verbose: bool,
+
+    /// Disable colors ( also disabled by NO_COLOR or non-terminal output )
+    #[structopt(long = "no-color")]
+    pub no_color: bool,
+
+    /// Show nothing, and tell the result by exit status only
+    #[structopt(short = "q", long = "quiet", conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Fail if the address differs from this ( exit status 5 )
+    #[structopt(long = "expect")]
+    pub expect: Option<IpAddr>,
+
+    #[structopt(subcommand)]
+    pub cmd: Option<Command>,
+}
+
+/// Protocol of `--only-protocol`
+#[derive(Debug)]
+pub enum ProtocolFilter {
+    /// All HTTP protocols
+    Http,
+    Protocol(ProviderInfoProtocol),
+}
+
+impl ProtocolFilter {
+    fn matches(&self, protocol: ProviderInfoProtocol) -> bool {
+        match self {
+            ProtocolFilter::Http => protocol.is_http(),
+            ProtocolFilter::Protocol(x) => *x == protocol,
+        }
+    }
+}
+
+impl std::str::FromStr for ProtocolFilter {
+    type Err = gip::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("http") {
+            Ok(ProtocolFilter::Http)
+        } else {
+            s.parse().map(ProtocolFilter::Protocol)
+        }
+    }
+}
+
+fn parse_family(s: &str) -> Result<ProviderInfoType, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "v4" | "ipv4" | "4" => Ok(ProviderInfoType::IPv4),
+        "v6" | "ipv6" | "6" => Ok(ProviderInfoType::IPv6),
+        _ => Err(format!(
+            "unknown address family: {} ( \"v4\" or \"v6\" )",
+            s
+        )),
+    }
+}
+
+/// strftime format of `--time-format`
+#[derive(Debug)]
+pub struct TimeFormat(String);
+
+impl TimeFormat {
+    fn format(&self, time: &DateTime<Utc>) -> String {
+        time.format(&self.0).to_string()
+    }
+}
+
+impl std::str::FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if StrftimeItems::new(s).any(|x| x == Item::Error) {
+            Err(format!("invalid strftime format: {}", s))
+        } else {
+            Ok(TimeFormat(String::from(s)))
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Measure and rank latency of providers
+    #[structopt(name = "bench")]
+    Bench {
+        /// Number of queries per each provider
+        #[structopt(short = "n", long = "count", default_value = "5")]
+        count: usize,
+
+        /// Reorder providers in the configuration file by the ranking
+        #[structopt(long = "write")]
+        write: bool,
+    },
+
+    /// Check all providers of both IPv4 and IPv6, and fail if any provider fails
+    #[structopt(name = "check")]
+    Check,
+
+    /// Compare the address through the proxy or source address with the direct one, and fail if they are the same
+    #[structopt(name = "leak")]
+    Leak,
+
+    /// Manage the configuration
+    #[structopt(name = "config")]
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCommand,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ConfigCommand {
+    /// Check the providers in the effective configuration without network access
+    #[structopt(name = "validate")]
+    Validate,
 }
 
 // -------------------------------------------------------------------------------------------------
 // Main
 // -------------------------------------------------------------------------------------------------
 
-fn main() -> Result<(), Error> {
-    run()
+fn main() {
+    let opt = Opt::from_args();
+    if let Err(e) = run_opt(&opt) {
+        if opt.show_json && !opt.quiet {
+            println!("{}", fmt_error_json(&e));
+        } else if !opt.quiet {
+            eprintln!("Error: {:?}", e);
+        }
+        std::process::exit(exit_code(&e));
+    }
+}
+
+/// Exit status of failure
+///
+/// * 1: generic failure
+/// * 2: all providers failed
+/// * 3: configuration error
+/// * 4: no address of requested family
+/// * 5: address differs from `--expect`
+fn exit_code(err: &Error) -> i32 {
+    if err.downcast_ref::<UnexpectedAddress>().is_some() {
+        return 5;
+    }
+    match err.chain().find_map(|x| x.downcast_ref::<gip::Error>()) {
+        Some(gip::Error::AllProvidersFailed { errors }) => {
+            if errors.iter().all(|(_, e)| is_unreachable(e)) {
+                4
+            } else {
+                2
+            }
+        }
+        Some(e) if e.is_config() => 3,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("address is {actual}, but expected {expected}")]
+struct UnexpectedAddress {
+    expected: IpAddr,
+    actual: IpAddr,
+}
+
+/// Returns true if the address family is not reachable from the host ( e.g. IPv6 on IPv4-only network )
+fn is_unreachable(err: &gip::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(x) = source {
+        if let Some(e) = x.downcast_ref::<std::io::Error>() {
+            return matches!(
+                e.kind(),
+                std::io::ErrorKind::NetworkUnreachable | std::io::ErrorKind::AddrNotAvailable
+            );
+        }
+        source = x.source();
+    }
+    false
 }
 
 pub fn run() -> Result<(), Error> {
@@ -87,8 +369,9 @@ pub fn run() -> Result<(), Error> {
 }
 
 pub fn run_opt(opt: &Opt) -> Result<(), Error> {
-    let giprc = match home_dir() {
-        Some(mut p) => {
+    let giprc = match (&opt.config, home_dir()) {
+        (Some(p), _) => Some(p.clone()),
+        (None, Some(mut p)) => {
             p.push(".gip.toml");
             if p.exists() {
                 Some(p)
@@ -96,66 +379,1075 @@ pub fn run_opt(opt: &Opt) -> Result<(), Error> {
                 None
             }
         }
-        None => None,
+        (None, None) => None,
     };
 
-    let mut client = match giprc {
-        Some(p) => {
-            let mut f =
-                File::open(&p).context(format!("failed to open {}", p.to_string_lossy()))?;
+    let text = match giprc {
+        Some(ref p) if p.as_os_str() == "-" => {
+            let mut s = String::new();
+            std::io::stdin()
+                .read_to_string(&mut s)
+                .context("failed to read stdin")?;
+            s
+        }
+        Some(ref p) => {
+            let mut f = File::open(p).map_err(|e| gip::Error::ConfigInvalid {
+                path: p.to_string_lossy().into(),
+                message: format!("failed to open ({})", e),
+            })?;
             let mut s = String::new();
             let _ = f.read_to_string(&mut s);
-            ProviderAny::from_toml(&s)?
+            s
+        }
+        None => String::from(gip::DEFAULT_TOML),
+    };
+    // providers from the remote and default lists follow the ones in the config,
+    // so the positions below `configured` match [[providers]] tables in `text`
+    let mut list = ProviderInfoList::from_toml(&text)?;
+    let configured = list.providers.len();
+    let cache_dir = opt
+        .cache_dir
+        .clone()
+        .or_else(|| dirs::cache_dir().map(|x| x.join("gip")));
+    list = list.load_remote(cache_dir.as_deref())?.merge_defaults()?;
+
+    // only the configured providers are persisted in the cache ( not `--url` )
+    let known: BTreeSet<String> = list.providers.iter().map(|x| x.id()).collect();
+
+    let mut ptype = if opt.v6 {
+        ProviderInfoType::IPv6
+    } else if opt.v4 {
+        ProviderInfoType::IPv4
+    } else if let Some(prefer) = opt.prefer {
+        prefer
+    } else if let Some(addr) = opt.expect.or(opt.source_ip) {
+        if addr.is_ipv6() {
+            ProviderInfoType::IPv6
+        } else {
+            ProviderInfoType::IPv4
         }
-        None => ProviderAny::from_toml(&gip::DEFAULT_TOML)?,
+    } else {
+        list.settings.ptype.unwrap_or(ProviderInfoType::IPv4)
     };
 
-    if opt.v6 {
-        client.ptype = ProviderInfoType::IPv6;
+    if let Some(ref url) = opt.url {
+        list.providers = vec![ProviderInfo::new()
+            .name(url)
+            .ptype(ptype)
+            .protocol(opt.protocol.unwrap_or(ProviderInfoProtocol::HttpPlane))
+            .url(url)
+            .key(&opt.key)];
+    }
+
+    #[cfg(feature = "geoip")]
+    let geoip = list.geoip.clone();
+    #[cfg(not(feature = "geoip"))]
+    let geoip: Option<()> = None;
+
+    let (mut client, indices) = select_providers(opt, &list, geoip.is_some())?;
+    client.ptype = ptype;
+    if opt.parallel.is_some() {
+        client.parallel = opt.parallel;
     }
 
     if opt.show_list {
-        for p in &client.providers {
-            println!("{:?}: {}", p.get_type(), p.get_name());
+        print_list(opt, &client);
+        return Ok(());
+    }
+
+    if let Some(timeout) = opt.timeout {
+        client.set_timeout(Duration::from_millis(timeout as u64));
+    }
+
+    if let Some(timeout) = opt.connect_timeout {
+        client.set_connect_timeout(Duration::from_millis(timeout as u64));
+    }
+
+    if let Some(timeout) = opt.read_timeout {
+        client.set_read_timeout(Duration::from_millis(timeout as u64));
+    }
+
+    let mut proxy = list.settings.proxy.clone();
+    if let Some(ref proxy_str) = opt.proxy {
+        let proxy_url = if proxy_str.contains("://") {
+            proxy_str.clone()
+        } else {
+            format!("http://{}", proxy_str)
+        };
+        url::Url::parse(&proxy_url).context(format!("failed to parse proxy: {}", proxy_str))?;
+        client.set_proxy_url(&proxy_url);
+        proxy = Some(proxy_url);
+    }
+
+    // Tor is slower than direct access
+    let tor_timeout = opt
+        .timeout
+        .map(|x| Duration::from_millis(x as u64))
+        .or(list.settings.timeout)
+        .unwrap_or(Duration::from_millis(10000));
+    if opt.check_tor {
+        if proxy.is_none() {
+            client.set_proxy_url(gip::TOR_PROXY);
+            proxy = Some(String::from(gip::TOR_PROXY));
+        }
+        client.set_timeout(tor_timeout);
+    }
+
+    if let Some(ref user_agent) = opt.user_agent {
+        client.set_user_agent(user_agent);
+    }
+
+    if opt.require_global {
+        client.set_require_global(true);
+    }
+
+    if opt.raw_response {
+        client.set_capture_response(true);
+    }
+
+    if let Some(addr) = opt.source_ip {
+        client.set_local_address(addr);
+    } else if let Some(ref name) = opt.interface {
+        client.set_local_address(interface_addr(name, ptype)?);
+    }
+
+    if let Some(Command::Config {
+        cmd: ConfigCommand::Validate,
+    }) = opt.cmd
+    {
+        return run_validate(opt, &list, &indices);
+    }
+
+    if let Some(Command::Bench { count, write }) = opt.cmd {
+        let ranking = run_bench(opt, &client, &list, &indices, count)?;
+        if write {
+            if opt.config.as_ref().is_some_and(|x| x.as_os_str() == "-") {
+                anyhow::bail!("failed to write ranking: configuration was read from stdin");
+            }
+            let path = giprc
+                .or_else(|| home_dir().map(|x| x.join(".gip.toml")))
+                .context("failed to find home directory")?;
+            let ranking: Vec<_> = ranking
+                .into_iter()
+                .filter(|x| x.index < configured)
+                .collect();
+            write_ranking(&path, &text, &ranking)?;
         }
         return Ok(());
     }
 
-    client.set_timeout(opt.timeout);
+    if let Some(Command::Check) = opt.cmd {
+        return run_check(opt, &client, &list, &indices);
+    }
+
+    if let Some(Command::Leak) = opt.cmd {
+        let local = opt.source_ip.is_some() || opt.interface.is_some();
+        if proxy.is_none() && !local {
+            anyhow::bail!("no proxy or source address is specified");
+        }
+        return run_leak(opt, &mut client, local);
+    }
 
-    if opt.proxy.is_some() {
-        let proxy_str = opt.proxy.clone().unwrap();
-        let (host, port) = proxy_str.split_at(proxy_str.find(':').unwrap_or(0));
-        let port = port
-            .trim_matches(':')
-            .parse::<u16>()
-            .context(format!("failed to parse proxy: {}", proxy_str))?;
-        client.set_proxy(host, port);
+    // rate limited providers are skipped, and the order is learned across runs
+    let backoff_path = cache_dir.as_ref().map(|x| x.join("backoff.json"));
+    if let Some(ref path) = backoff_path {
+        load_backoff(&client, path);
+    }
+    let stats_path = cache_dir.map(|x| x.join("stats.json"));
+    if let Some(ref path) = stats_path {
+        load_stats(&client, path);
     }
 
-    let addr = client.get_addr()?;
-    let addr_str = if opt.v6 {
-        format!("{:?}", addr.v6addr.unwrap())
+    let (mut ret, mut attempts) = lookup(opt, &client);
+    if ret.is_err() && opt.prefer.is_some() {
+        ptype = match ptype {
+            ProviderInfoType::IPv4 => ProviderInfoType::IPv6,
+            ProviderInfoType::IPv6 => ProviderInfoType::IPv4,
+        };
+        if opt.verbose {
+            eprintln!("Fallback  : {:?}", ptype);
+        }
+        client.ptype = ptype;
+        (ret, attempts) = lookup(opt, &client);
+    }
+
+    if let Some(ref path) = backoff_path {
+        save_backoff(&client, path, &known);
+    }
+    if let Some(ref path) = stats_path {
+        save_stats(&client, path, &known);
+    }
+    let mut addr = ret?;
+    if opt.ptr {
+        addr.lookup_hostname()?;
+    }
+
+    #[cfg(feature = "geoip")]
+    if let Some(ref geoip) = geoip {
+        addr.lookup_geoip(geoip)?;
+    }
+
+    if opt.asn && addr.asn.is_none() {
+        addr.lookup_asn()?;
+    }
+
+    if opt.verbose || opt.prefer_stable {
+        addr.lookup_ipv6_privacy()?;
+        if opt.prefer_stable {
+            addr.prefer_stable();
+        }
+    }
+
+    let actual = if ptype == ProviderInfoType::IPv6 {
+        IpAddr::V6(addr.v6addr.unwrap())
+    } else {
+        IpAddr::V4(addr.v4addr.unwrap())
+    };
+    let addr_str = format!("{:?}", actual);
+
+    let tor = if opt.check_tor {
+        let proxy = proxy.as_deref().unwrap_or(gip::TOR_PROXY);
+        Some(gip::check_tor(proxy, tor_timeout).context("failed to check Tor exit")?)
     } else {
-        format!("{:?}", addr.v4addr.unwrap())
+        None
     };
 
+    if let Some(expected) = opt.expect {
+        if actual != expected {
+            return Err(UnexpectedAddress { expected, actual }.into());
+        }
+    }
+
+    if opt.quiet {
+        return Ok(());
+    }
+
     if opt.verbose {
         println!("IP Address: {}", addr_str);
+        if let Some(ref hostname) = addr.hostname {
+            println!("Hostname  : {}", hostname);
+        }
+        if let Some(ref privacy) = addr.ipv6_privacy {
+            println!("Temporary : {}", fmt_ipv6_privacy(privacy));
+        }
+        if let Some(ref asn) = addr.asn {
+            println!("ASN       : AS{} ( {} )", asn.asn, asn.organization);
+            println!("Prefix    : {}", asn.prefix);
+        }
+        if let Some(ref geo) = addr.geo {
+            println!("Location  : {}", fmt_geo(geo));
+        }
+        if let Some(ref tor) = tor {
+            println!("Tor Exit  : {}", fmt_tor(tor));
+        }
+        if opt.prefer.is_some() {
+            println!("Family    : {:?}", ptype);
+        }
         println!("Provider  : {}", addr.provider);
-        println!("Check Time: {}", addr.time);
+        match opt.time_format {
+            Some(ref format) => println!("Check Time: {}", format.format(&addr.time)),
+            None => println!("Check Time: {}", addr.time),
+        }
         println!("Latency   : {}ms", addr.latency.as_millis());
+        if let Some(ref response) = addr.raw_response {
+            println!("Response  : {:?}", response);
+        }
+        if !attempts.is_empty() {
+            println!("Attempts  :");
+            for line in fmt_attempts(&attempts, use_color(opt, &std::io::stdout())) {
+                println!("{}", line);
+            }
+        }
     } else {
         if opt.show_string {
             print!("{}", addr_str);
         } else if opt.show_json {
-            println!("{{\"{}\": \"{}\"}}", opt.json_key, addr_str);
+            let time = addr.time.to_rfc3339_opts(SecondsFormat::Millis, true);
+            let mut json = format!(
+                "{{\"{}\": \"{}\", \"time\": \"{}\"",
+                opt.json_key, addr_str, time
+            );
+            if opt.prefer.is_some() {
+                json.push_str(&format!(", \"family\": \"{:?}\"", ptype));
+            }
+            if let Some(ref tor) = tor {
+                json.push_str(&format!(", \"tor\": {}", tor.is_tor));
+            }
+            json.push('}');
+            println!("{}", json);
         } else {
             println!("{}", addr_str);
+            if let Some(asn) = addr.asn.as_ref().filter(|_| opt.asn) {
+                println!("AS{} {} ( {} )", asn.asn, asn.prefix, asn.organization);
+            }
+            if let Some(geo) = addr.geo.as_ref().filter(|_| opt.geo) {
+                println!("{}", fmt_geo(geo));
+            }
+            if let Some(ref tor) = tor {
+                println!("Tor exit: {}", fmt_tor(tor));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the client of the providers selected by the options, and their positions in the config
+///
+/// `client.providers[k]` is created from `list.providers[indices[k]]`.
+fn select_providers(
+    opt: &Opt,
+    list: &ProviderInfoList,
+    local_geo: bool,
+) -> Result<(ProviderAny, Vec<usize>), Error> {
+    // positions of providers in the config
+    let mut indices: Vec<usize> = (0..list.providers.len()).collect();
+    // disabled providers can be used by `--provider` explicitly
+    indices.retain(|&i| {
+        let name = &list.providers[i].name;
+        (list.providers[i].enabled || opt.provider.contains(name))
+            && !list.exclude.contains(name)
+            && !opt.exclude.contains(name)
+    });
+
+    if !opt.provider.is_empty() {
+        for name in &opt.provider {
+            if !list.providers.iter().any(|x| &x.name == name) {
+                anyhow::bail!("provider not found: {}", name);
+            }
+        }
+        indices.retain(|&i| opt.provider.contains(&list.providers[i].name));
+    }
+
+    if !opt.only_protocol.is_empty() {
+        indices.retain(|&i| {
+            let protocol = list.providers[i].protocol;
+            opt.only_protocol.iter().any(|x| x.matches(protocol))
+        });
+    }
+
+    // geolocation is given by local databases if configured
+    if opt.geo && !local_geo {
+        indices.retain(|&i| list.providers[i].geo.is_some());
+    }
+
+    // options override [settings], and `indices` must follow the providers kept by `secure_only`
+    let mut settings = list.settings.clone();
+    settings.secure_only |= opt.secure_only;
+    if settings.secure_only {
+        indices.retain(|&i| list.providers[i].is_secure());
+        if indices.is_empty() {
+            return Err(gip::Error::ConfigInvalid {
+                path: String::from("settings.secure_only"),
+                message: String::from(
+                    "no secure provider is available ( only HTTPS providers are secure )",
+                ),
+            }
+            .into());
+        }
+    }
+
+    // `leak` compares the proxy of the options with the direct access,
+    // so the proxy of each provider is ignored in both of them
+    let leak = matches!(opt.cmd, Some(Command::Leak));
+    let mut client = ProviderAny::new();
+    client.providers = indices
+        .iter()
+        .map(|&i| {
+            let mut info = list.providers[i].clone();
+            if leak {
+                info.proxy = None;
+            }
+            info.create()
+        })
+        .collect();
+
+    client.order = Order::Adaptive;
+    client.apply_settings(&settings);
+    Ok((client, indices))
+}
+
+/// Restore rate limited providers from the file of `save_backoff`
+fn load_backoff(client: &ProviderAny, path: &std::path::Path) {
+    let backoff: BTreeMap<String, u64> = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default();
+    for (name, until) in backoff {
+        client.set_backoff(&name, UNIX_EPOCH + Duration::from_secs(until));
+    }
+}
+
+/// Save rate limited providers in `known` by UNIX time until they are skipped
+fn save_backoff(client: &ProviderAny, path: &std::path::Path, known: &BTreeSet<String>) {
+    let backoff: BTreeMap<_, _> = client
+        .backoff()
+        .into_iter()
+        .filter(|(name, _)| known.contains(name))
+        .map(|(name, until)| {
+            let until = until.duration_since(UNIX_EPOCH).unwrap_or_default();
+            (name, until.as_secs() + 1)
+        })
+        .collect();
+    if backoff.is_empty() && !path.exists() {
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(path, serde_json::to_string(&backoff).unwrap_or_default());
+}
+
+/// Restore provider statistics from the file of `save_stats`
+fn load_stats(client: &ProviderAny, path: &std::path::Path) {
+    type Saved = (u32, u32, u64, Option<u64>);
+    let stats: BTreeMap<String, Saved> = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default();
+    for (name, (successes, failures, latency, success_latency)) in stats {
+        let stats = ProviderStats {
+            successes,
+            failures,
+            latency: Duration::from_millis(latency),
+            success_latency: success_latency.map(Duration::from_millis),
+        };
+        client.set_stats(&name, stats);
+    }
+}
+
+/// Save provider statistics in `known` by ( successes, failures, latency, success_latency ),
+/// and latencies are milliseconds
+///
+/// The entries of the providers removed from the config are dropped.
+fn save_stats(client: &ProviderAny, path: &std::path::Path, known: &BTreeSet<String>) {
+    let stats: BTreeMap<_, _> = client
+        .stats()
+        .into_iter()
+        .filter(|(name, _)| known.contains(name))
+        .map(|(name, x)| {
+            (
+                name,
+                (
+                    x.successes,
+                    x.failures,
+                    x.latency.as_millis() as u64,
+                    x.success_latency.map(|x| x.as_millis() as u64),
+                ),
+            )
+        })
+        .collect();
+    if stats.is_empty() && !path.exists() {
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(path, serde_json::to_string(&stats).unwrap_or_default());
+}
+
+/// Find the address of the network interface by the type
+fn interface_addr(name: &str, ptype: ProviderInfoType) -> Result<IpAddr, Error> {
+    let addrs: Vec<_> = if_addrs::get_if_addrs()
+        .context("failed to get network interfaces")?
+        .into_iter()
+        .filter(|x| x.name == name)
+        .map(|x| x.ip())
+        .filter(|x| x.is_ipv6() == (ptype == ProviderInfoType::IPv6))
+        .collect();
+    // link-local address can't reach providers
+    addrs
+        .iter()
+        .find(|x| !matches!(x, IpAddr::V6(x) if x.is_unicast_link_local()))
+        .or(addrs.first())
+        .copied()
+        .context(format!("no {:?} address of interface: {}", ptype, name))
+}
+
+const BOLD: &str = "1";
+const RED: &str = "31";
+const GREEN: &str = "32";
+
+/// Whether colors are used for the stream
+fn use_color(opt: &Opt, stream: &impl std::io::IsTerminal) -> bool {
+    let no_color = matches!(std::env::var_os("NO_COLOR"), Some(x) if !x.is_empty());
+    !opt.no_color && !no_color && stream.is_terminal()
+}
+
+/// Decorate the text by ANSI escape sequence of the style
+fn paint(text: &str, style: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        String::from(text)
+    }
+}
+
+/// Style of the result column
+fn result_style(result: &str) -> Option<&'static str> {
+    match result {
+        "OK" => Some(GREEN),
+        "FAIL" | "INVALID" => Some(RED),
+        _ => None,
+    }
+}
+
+/// Get the address by the strategy, and show the attempts to stderr by `--verbose` if it fails
+fn lookup(
+    opt: &Opt,
+    client: &ProviderAny,
+) -> (Result<gip::GlobalAddress, gip::Error>, Vec<Attempt>) {
+    if opt.consensus || client.strategy == Strategy::Consensus {
+        return (client.get_addr_consensus(), Vec::new());
+    }
+    let (ret, attempts) = client.get_addr_with_attempts(&CancellationToken::new());
+    if opt.verbose && ret.is_err() {
+        eprintln!("Attempts  :");
+        for line in fmt_attempts(&attempts, use_color(opt, &std::io::stderr())) {
+            eprintln!("{}", line);
+        }
+    }
+    (ret, attempts)
+}
+
+fn fmt_attempts(attempts: &[Attempt], color: bool) -> Vec<String> {
+    let width = attempts.iter().map(|x| x.name.len()).max().unwrap_or(0);
+    attempts
+        .iter()
+        .map(|x| {
+            let result = if x.error.is_none() { "OK" } else { "FAIL" };
+            let line = format!(
+                "    {:width$}  {}{}  {:>6}",
+                x.name,
+                paint(result, result_style(result).unwrap(), color),
+                " ".repeat(4 - result.len()),
+                format!("{}ms", x.latency.as_millis()),
+                width = width
+            );
+            match x.error {
+                Some(ref e) => format!("{}  {}", line, e),
+                None => line,
+            }
+        })
+        .collect()
+}
+
+/// Structured error document for `--json`
+fn fmt_error_json(err: &Error) -> serde_json::Value {
+    let mut ret = serde_json::json!({ "kind": "other", "message": format!("{:#}", err) });
+    if let Some(e) = err.downcast_ref::<UnexpectedAddress>() {
+        ret["kind"] = "unexpected_address".into();
+        ret["expected"] = e.expected.to_string().into();
+        ret["actual"] = e.actual.to_string().into();
+    }
+    if let Some(e) = err.chain().find_map(|x| x.downcast_ref::<gip::Error>()) {
+        ret["kind"] = error_name(e).into();
+        ret["category"] = format!("{:?}", e.kind()).to_lowercase().into();
+        if let gip::Error::AllProvidersFailed { ref errors } = e {
+            let providers: Vec<_> = errors
+                .iter()
+                .map(|(name, e)| {
+                    let mut message = e.to_string();
+                    let mut source = std::error::Error::source(e);
+                    while let Some(x) = source {
+                        message.push_str(&format!(": {}", x));
+                        source = x.source();
+                    }
+                    serde_json::json!({
+                        "name": name,
+                        "kind": error_name(e),
+                        "category": format!("{:?}", e.kind()).to_lowercase(),
+                        "message": message,
+                    })
+                })
+                .collect();
+            ret["providers"] = providers.into();
+        }
+    }
+    serde_json::json!({ "error": ret })
+}
+
+/// Variant name of the error in snake_case ( e.g. "all_providers_failed" )
+fn error_name(err: &gip::Error) -> String {
+    let mut ret = String::new();
+    for c in format!("{:?}", err).chars() {
+        if !c.is_ascii_alphanumeric() {
+            break;
+        }
+        if c.is_ascii_uppercase() && !ret.is_empty() {
+            ret.push('_');
+        }
+        ret.push(c.to_ascii_lowercase());
+    }
+    ret
+}
+
+fn fmt_tor(tor: &gip::TorCheck) -> String {
+    if tor.is_tor {
+        format!("yes ( {} )", tor.addr)
+    } else {
+        format!("no ( {} )", tor.addr)
+    }
+}
+
+fn fmt_ipv6_privacy(privacy: &gip::Ipv6Privacy) -> String {
+    let temporary = if privacy.temporary { "yes" } else { "no" };
+    match privacy.stable {
+        Some(stable) if privacy.temporary => format!("{} ( stable: {} )", temporary, stable),
+        _ => String::from(temporary),
+    }
+}
+
+fn fmt_geo(geo: &GeoInfo) -> String {
+    let mut ret = [&geo.city, &geo.region, &geo.country]
+        .iter()
+        .filter_map(|x| x.as_deref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Some(ref org) = geo.organization {
+        ret.push_str(&format!(" ( {} )", org));
+    }
+    ret
+}
+
+// -------------------------------------------------------------------------------------------------
+// Bench
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+struct BenchResult {
+    /// Position of provider in the config
+    index: usize,
+    success: usize,
+    count: usize,
+    /// Sorted latencies of successful queries
+    latencies: Vec<Duration>,
+}
+
+impl BenchResult {
+    fn success_rate(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.success as f64 / self.count as f64
         }
     }
 
-    return Ok(());
+    /// Latency of the percentile by nearest-rank method
+    fn percentile(&self, p: usize) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (p * self.latencies.len()).div_ceil(100).max(1);
+        Some(self.latencies[rank - 1])
+    }
+}
+
+/// Number of providers queried at once by `--parallel`
+fn chunk_size(client: &ProviderAny, len: usize) -> usize {
+    client.parallel.map_or(len, |x| x.get()).max(1)
+}
+
+/// Query each provider `count` times, print the ranking and return it
+fn run_bench(
+    opt: &Opt,
+    client: &ProviderAny,
+    list: &ProviderInfoList,
+    indices: &[usize],
+    count: usize,
+) -> Result<Vec<BenchResult>, Error> {
+    let providers: Vec<_> = client
+        .providers
+        .iter()
+        .zip(indices)
+        .filter(|(p, _)| p.get_type() == client.ptype)
+        .collect();
+    let mut results: Vec<BenchResult> = Vec::new();
+    for chunk in providers.chunks(chunk_size(client, providers.len())) {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|&(p, &index)| {
+                    s.spawn(move || {
+                        let mut latencies = Vec::new();
+                        for _ in 0..count {
+                            if let Ok(addr) = p.get_addr() {
+                                latencies.push(addr.latency);
+                            }
+                        }
+                        latencies.sort();
+                        BenchResult {
+                            index,
+                            success: latencies.len(),
+                            count,
+                            latencies,
+                        }
+                    })
+                })
+                .collect();
+            results.extend(handles.into_iter().map(|h| h.join().unwrap()));
+        });
+    }
+
+    // higher success rate, and then lower median latency is better
+    results.sort_by(|a, b| {
+        b.success
+            .cmp(&a.success)
+            .then_with(|| a.percentile(50).cmp(&b.percentile(50)))
+    });
+
+    let ms = |x: Option<Duration>| x.map(|x| x.as_millis() as u64);
+    if opt.show_json && !opt.quiet {
+        let json: Vec<_> = results
+            .iter()
+            .map(|x| {
+                let info = &list.providers[x.index];
+                serde_json::json!({
+                    "name": info.name,
+                    "url": info.url,
+                    "success_rate": x.success_rate(),
+                    "min_ms": ms(x.percentile(0)),
+                    "median_ms": ms(x.percentile(50)),
+                    "p95_ms": ms(x.percentile(95)),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+    } else if !opt.quiet {
+        let fmt = |x: Option<Duration>| ms(x).map_or(String::from("-"), |x| format!("{}ms", x));
+        let rows: Vec<_> = results
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let info = &list.providers[x.index];
+                [
+                    format!("{}", i + 1),
+                    info.name.clone(),
+                    info.url.clone(),
+                    format!("{:.0}%", x.success_rate() * 100.0),
+                    fmt(x.percentile(0)),
+                    fmt(x.percentile(50)),
+                    fmt(x.percentile(95)),
+                ]
+            })
+            .collect();
+        let header =
+            ["Rank", "Provider", "URL", "Success", "Min", "Median", "P95"].map(String::from);
+        print_table(&header, &rows, use_color(opt, &std::io::stdout()));
+    }
+
+    Ok(results)
+}
+
+/// Show the effective provider list
+fn print_list(opt: &Opt, client: &ProviderAny) {
+    if opt.quiet {
+        return;
+    }
+    let protocol = |p: &dyn Provider| p.get_protocol().map(|x| format!("{:?}", x));
+    if opt.show_json {
+        let json: Vec<_> = client
+            .providers
+            .iter()
+            .map(|p| {
+                serde_json::json!({
+                    "name": p.get_name(),
+                    "ptype": format!("{:?}", p.get_type()),
+                    "protocol": protocol(p.as_ref()),
+                    "url": p.get_url(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+    } else {
+        let rows: Vec<_> = client
+            .providers
+            .iter()
+            .map(|p| {
+                [
+                    p.get_name(),
+                    format!("{:?}", p.get_type()),
+                    protocol(p.as_ref()).unwrap_or_else(|| String::from("-")),
+                    String::from(p.get_url().unwrap_or("-")),
+                ]
+            })
+            .collect();
+        let header = ["Provider", "Type", "Protocol", "URL"].map(String::from);
+        print_table(&header, &rows, use_color(opt, &std::io::stdout()));
+    }
+}
+
+/// Print the table with bold header, and "OK" / "FAIL" cells in green / red if `color`
+fn print_table<const N: usize>(header: &[String; N], rows: &[[String; N]], color: bool) {
+    let mut width = [0; N];
+    for row in std::iter::once(header).chain(rows) {
+        for (w, x) in width.iter_mut().zip(row) {
+            *w = (*w).max(x.chars().count());
+        }
+    }
+    for (i, row) in std::iter::once(header).chain(rows).enumerate() {
+        let line: Vec<_> = row
+            .iter()
+            .zip(width)
+            .map(|(x, w)| {
+                let style = if i == 0 { Some(BOLD) } else { result_style(x) };
+                let padding = " ".repeat(w - x.chars().count());
+                match style {
+                    Some(style) => format!("{}{}", paint(x, style, color), padding),
+                    None => format!("{}{}", x, padding),
+                }
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+/// Write the config reordered by the ranking to `path`
+fn write_ranking(path: &std::path::Path, text: &str, ranking: &[BenchResult]) -> Result<(), Error> {
+    let text = reorder_providers(text, &ranking.iter().map(|x| x.index).collect::<Vec<_>>())
+        .context("failed to find [[providers]] tables in the config")?;
+    std::fs::write(path, text).context(format!("failed to write {}", path.to_string_lossy()))?;
+    Ok(())
+}
+
+/// Reorder `[[providers]]` tables in TOML text.
+/// The tables at the positions of `ranked` are sorted by the order of `ranked`, and the others are not moved.
+fn reorder_providers(text: &str, ranked: &[usize]) -> Option<String> {
+    // split into (is_provider, text) segments to keep comments and other tables
+    let mut segments: Vec<(bool, String)> = Vec::new();
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("[[providers]]") {
+            segments.push((true, String::new()));
+        } else if segments.is_empty()
+            || (trimmed.starts_with('[')
+                && !trimmed.starts_with("[providers.")
+                && !trimmed.starts_with("[[providers."))
+        {
+            segments.push((false, String::new()));
+        }
+        segments.last_mut().unwrap().1.push_str(line);
+    }
+
+    let blocks: Vec<String> = segments
+        .iter()
+        .filter(|x| x.0)
+        .map(|x| {
+            let mut block = x.1.clone();
+            if !block.ends_with('\n') {
+                block.push('\n');
+            }
+            block
+        })
+        .collect();
+    if ranked.iter().any(|&x| x >= blocks.len()) {
+        return None;
+    }
+
+    let mut slots = ranked.to_vec();
+    slots.sort_unstable();
+    let mut order: Vec<usize> = (0..blocks.len()).collect();
+    for (slot, &index) in slots.iter().zip(ranked) {
+        order[*slot] = index;
+    }
+    let mut reordered = order.iter().map(|&x| &blocks[x]);
+
+    let mut ret = String::new();
+    for (is_provider, text) in &segments {
+        if *is_provider {
+            ret.push_str(reordered.next().unwrap());
+        } else {
+            ret.push_str(text);
+        }
+    }
+    Some(ret)
+}
+
+// -------------------------------------------------------------------------------------------------
+// Check
+// -------------------------------------------------------------------------------------------------
+
+/// Query all providers once, print the results and fail if any provider fails
+/// Result and latency of a provider by `check`
+type CheckResult = (Result<gip::GlobalAddress, gip::Error>, Duration);
+
+fn run_check(
+    opt: &Opt,
+    client: &ProviderAny,
+    list: &ProviderInfoList,
+    indices: &[usize],
+) -> Result<(), Error> {
+    let results = check_all(client);
+    let addr_str = |addr: &gip::GlobalAddress| addr.addr().map_or(String::new(), |x| x.to_string());
+    if opt.show_json && !opt.quiet {
+        let json: Vec<_> = results
+            .iter()
+            .zip(indices)
+            .map(|((ret, latency), &i)| {
+                let info = &list.providers[i];
+                serde_json::json!({
+                    "type": format!("{:?}", info.ptype),
+                    "name": info.name,
+                    "url": info.url,
+                    "ok": ret.is_ok(),
+                    "latency_ms": latency.as_millis() as u64,
+                    "addr": ret.as_ref().ok().map(addr_str),
+                    "error": ret.as_ref().err().map(|e| e.to_string()),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+    } else if !opt.quiet {
+        let rows = check_rows(list, indices, &results);
+        let header = ["Type", "Provider", "URL", "Result", "Latency", "Address"].map(String::from);
+        print_table(&header, &rows, use_color(opt, &std::io::stdout()));
+    }
+
+    let failed = results.iter().filter(|x| x.0.is_err()).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} providers failed", failed, results.len());
+    }
+    Ok(())
+}
+
+/// Query all providers concurrently with the latency
+fn check_all(client: &ProviderAny) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for chunk in client
+        .providers
+        .chunks(chunk_size(client, client.providers.len()))
+    {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|p| {
+                    s.spawn(move || {
+                        let start = Instant::now();
+                        let ret = p.get_addr();
+                        (ret, start.elapsed())
+                    })
+                })
+                .collect();
+            results.extend(handles.into_iter().map(|h| h.join().unwrap()));
+        });
+    }
+    results
+}
+
+/// Table rows of `check`, and `results` are in the order of `indices`
+fn check_rows(
+    list: &ProviderInfoList,
+    indices: &[usize],
+    results: &[CheckResult],
+) -> Vec<[String; 6]> {
+    let addr_str = |addr: &gip::GlobalAddress| addr.addr().map_or(String::new(), |x| x.to_string());
+    results
+        .iter()
+        .zip(indices)
+        .map(|((ret, latency), &i)| {
+            let info = &list.providers[i];
+            [
+                format!("{:?}", info.ptype),
+                info.name.clone(),
+                info.url.clone(),
+                String::from(if ret.is_ok() { "OK" } else { "FAIL" }),
+                format!("{}ms", latency.as_millis()),
+                match ret {
+                    Ok(addr) => addr_str(addr),
+                    Err(e) => e.to_string(),
+                },
+            ]
+        })
+        .collect()
+}
+
+fn run_validate(opt: &Opt, list: &ProviderInfoList, indices: &[usize]) -> Result<(), Error> {
+    let results: Vec<_> = indices
+        .iter()
+        .map(|&i| (&list.providers[i], list.providers[i].validate()))
+        .collect();
+
+    if opt.show_json && !opt.quiet {
+        let json: Vec<_> = results
+            .iter()
+            .map(|(info, problems)| {
+                serde_json::json!({
+                    "type": format!("{:?}", info.ptype),
+                    "name": info.name,
+                    "protocol": format!("{:?}", info.protocol),
+                    "url": info.url,
+                    "ok": problems.is_empty(),
+                    "problems": problems,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+    } else if !opt.quiet {
+        let rows: Vec<_> = results
+            .iter()
+            .map(|(info, problems)| {
+                [
+                    format!("{:?}", info.ptype),
+                    info.name.clone(),
+                    format!("{:?}", info.protocol),
+                    info.url.clone(),
+                    String::from(if problems.is_empty() { "OK" } else { "INVALID" }),
+                    problems.join("; "),
+                ]
+            })
+            .collect();
+        let header =
+            ["Type", "Provider", "Protocol", "URL", "Result", "Problems"].map(String::from);
+        print_table(&header, &rows, use_color(opt, &std::io::stdout()));
+    }
+
+    let invalid = results.iter().filter(|x| !x.1.is_empty()).count();
+    if invalid > 0 {
+        anyhow::bail!("{} of {} providers are invalid", invalid, results.len());
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------------------------------
+// Leak
+// -------------------------------------------------------------------------------------------------
+
+fn run_leak(opt: &Opt, client: &mut ProviderAny, local: bool) -> Result<(), Error> {
+    let proxied = client.get_addr()?;
+
+    // unspecified address means the default route
+    client.set_proxy_url("");
+    if local {
+        client.set_local_address(match client.ptype {
+            ProviderInfoType::IPv4 => IpAddr::from([0; 4]),
+            ProviderInfoType::IPv6 => IpAddr::from([0; 16]),
+        });
+    }
+    let direct = client.get_addr()?;
+
+    let leak = proxied.addr() == direct.addr();
+    let addr_str = |addr: &gip::GlobalAddress| addr.addr().map_or(String::new(), |x| x.to_string());
+    if opt.show_json && !opt.quiet {
+        let json = serde_json::json!({
+            "proxied": addr_str(&proxied),
+            "direct": addr_str(&direct),
+            "leak": leak,
+        });
+        println!("{}", json);
+    } else if !opt.quiet {
+        println!(
+            "Proxied   : {} ( {} )",
+            addr_str(&proxied),
+            proxied.provider
+        );
+        println!("Direct    : {} ( {} )", addr_str(&direct), direct.provider);
+        println!("Leak      : {}", if leak { "yes" } else { "no" });
+    }
+
+    if leak {
+        anyhow::bail!("the address through the proxy is the same as the direct one");
+    }
+    Ok(())
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -165,58 +1457,741 @@ pub fn run_opt(opt: &Opt) -> Result<(), Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Parse arguments, and keep the cache in a temporary directory instead of the user's one
+    fn parse<I>(args: I) -> Opt
+    where
+        I: IntoIterator,
+        I::Item: Into<std::ffi::OsString> + Clone,
+    {
+        let mut opt = Opt::from_iter(args);
+        opt.cache_dir = Some(std::env::temp_dir().join(format!("gip-test-{}", std::process::id())));
+        opt
+    }
 
     #[test]
+    #[ignore = "requires network"]
     fn test_run() {
-        let args = vec!["gip"];
-        let opt = Opt::from_iter(args.iter());
+        let args = ["gip"];
+        let opt = parse(args.iter());
         let ret = run_opt(&opt);
         assert!(ret.is_ok());
     }
 
     #[test]
+    #[ignore = "requires network"]
     fn test_verbose() {
-        let args = vec!["gip", "-v"];
-        let opt = Opt::from_iter(args.iter());
+        let args = ["gip", "-v"];
+        let opt = parse(args.iter());
         let ret = run_opt(&opt);
         assert!(ret.is_ok());
     }
 
     #[test]
+    #[ignore = "requires network"]
     fn test_string() {
-        let args = vec!["gip", "-s"];
-        let opt = Opt::from_iter(args.iter());
+        let args = ["gip", "-s"];
+        let opt = parse(args.iter());
         let ret = run_opt(&opt);
         assert!(ret.is_ok());
     }
 
     #[test]
+    #[ignore = "requires network"]
     fn test_json() {
-        let args = vec!["gip", "-j"];
-        let opt = Opt::from_iter(args.iter());
+        let args = ["gip", "-j"];
+        let opt = parse(args.iter());
         let ret = run_opt(&opt);
         assert!(ret.is_ok());
     }
 
+    /// Start a TCP server replying `reply` to every connection
+    fn tcp_server(reply: &'static str) -> String {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = server.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for mut stream in server.incoming().flatten() {
+                let _ = std::io::Write::write_all(&mut stream, reply.as_bytes());
+            }
+        });
+        url
+    }
+
+    /// Start a HTTP server returning `body` to any request, and count the requests
+    fn http_server(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count = requests.clone();
+        std::thread::spawn(move || {
+            for mut stream in server.incoming().flatten() {
+                let mut reader = std::io::BufReader::new(&stream);
+                let mut line = String::new();
+                while std::io::BufRead::read_line(&mut reader, &mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                count.fetch_add(1, Ordering::SeqCst);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = std::io::Write::write_all(&mut stream, response.as_bytes());
+            }
+        });
+        (addr, requests)
+    }
+
+    /// Write a config file replacing the default providers by `providers`
+    fn config_file(name: &str, providers: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gip-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, format!("replace_defaults = true\n{}", providers)).unwrap();
+        path
+    }
+
+    /// TOML of a TcpPlane provider
+    fn tcp_provider(name: &str, url: &str) -> String {
+        format!(
+            "[[providers]]\nname = \"{}\"\nptype = \"IPv4\"\nprotocol = \"TcpPlane\"\nurl = \"{}\"\nkey = []\n",
+            name, url
+        )
+    }
+
+    #[test]
+    fn test_run_local() {
+        let url = tcp_server("192.0.2.1\n");
+        for flag in ["-4", "-v", "-s", "-j"] {
+            let args = ["gip", "--url", &url, "--protocol", "tcp", flag];
+            let opt = parse(args.iter());
+            assert!(run_opt(&opt).is_ok(), "{}", flag);
+        }
+
+        let args = [
+            "gip",
+            "--url",
+            &url,
+            "--protocol",
+            "tcp",
+            "-v",
+            "--raw-response",
+        ];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+        assert!(Opt::from_iter_safe(["gip", "--raw-response"].iter()).is_err());
+    }
+
+    #[test]
+    fn test_cache_dir() {
+        let dir = std::env::temp_dir().join(format!("gip-test-cache-{}", std::process::id()));
+        let stats = dir.join("stats.json");
+        let list = ProviderInfoList::from_toml(gip::DEFAULT_TOML).unwrap();
+        let known = list.providers[0].id();
+        let entry = serde_json::json!([1, 0, 10, 10]);
+        let saved = serde_json::json!({ &known: entry, "removed": entry });
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&stats, saved.to_string()).unwrap();
+
+        let url = tcp_server("192.0.2.1\n");
+        let args = ["gip", "--url", &url, "--protocol", "tcp"];
+        let mut opt = Opt::from_iter(args.iter());
+        opt.cache_dir = Some(dir.clone());
+        assert!(run_opt(&opt).is_ok());
+
+        // `--url` and the providers removed from the config are not kept
+        let saved: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(&stats).unwrap()).unwrap();
+        assert_eq!(saved.keys().collect::<Vec<_>>(), vec![&known]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_time_format() {
+        let time = DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let format: TimeFormat = "%Y-%m-%dT%H:%M:%S%:z".parse().unwrap();
+        assert_eq!(format.format(&time), "2024-01-02T03:04:05+00:00");
+        assert!("%!".parse::<TimeFormat>().is_err());
+
+        let url = tcp_server("192.0.2.1\n");
+        let args = [
+            "gip",
+            "--url",
+            &url,
+            "--protocol",
+            "tcp",
+            "-v",
+            "--time-format",
+            "%s",
+        ];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+        assert!(Opt::from_iter_safe(["gip", "--time-format", "%!"].iter()).is_err());
+    }
+
+    #[test]
+    fn test_fmt_ipv6_privacy() {
+        let privacy = gip::Ipv6Privacy {
+            temporary: true,
+            stable: Some("2001:db8::1".parse().unwrap()),
+        };
+        assert_eq!(fmt_ipv6_privacy(&privacy), "yes ( stable: 2001:db8::1 )");
+        let privacy = gip::Ipv6Privacy {
+            temporary: false,
+            stable: Some("2001:db8::1".parse().unwrap()),
+        };
+        assert_eq!(fmt_ipv6_privacy(&privacy), "no");
+    }
+
     #[test]
     fn test_list() {
-        let args = vec!["gip", "-l"];
-        let opt = Opt::from_iter(args.iter());
+        let args = ["gip", "-l"];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        assert!(ret.is_ok());
+    }
+
+    #[test]
+    fn test_list_json() {
+        let args = ["gip", "-l", "-j", "--provider", "ident.me"];
+        let opt = parse(args.iter());
         let ret = run_opt(&opt);
         assert!(ret.is_ok());
     }
 
+    #[test]
+    fn test_prefer() {
+        let url = tcp_server("192.0.2.1\n");
+        let path = std::env::temp_dir().join(format!("gip-prefer-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            format!(
+                r#"
+                [[providers]]
+                    name     = "local-v4"
+                    ptype    = "IPv4"
+                    protocol = "TcpPlane"
+                    url      = "{}"
+                    key      = []
+
+                [[providers]]
+                    name     = "local-v6"
+                    ptype    = "IPv6"
+                    protocol = "TcpPlane"
+                    url      = "[::1]:1"
+                    key      = []
+            "#,
+                url
+            ),
+        )
+        .unwrap();
+        let config = path.to_str().unwrap();
+        let providers = ["--provider", "local-v4", "--provider", "local-v6"];
+
+        let mut args = vec!["gip", "--config", config, "--prefer", "v6", "-v"];
+        args.extend(providers);
+        let ret = run_opt(&parse(args.iter()));
+
+        let mut args = vec!["gip", "--config", config, "-6"];
+        args.extend(providers);
+        let ret_v6 = run_opt(&parse(args.iter()));
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_ok());
+        assert!(ret_v6.is_err());
+
+        assert_eq!(parse_family("v6"), Ok(ProviderInfoType::IPv6));
+        assert!(parse_family("v5").is_err());
+        assert!(Opt::from_iter_safe(["gip", "--prefer", "v6", "-4"].iter()).is_err());
+    }
+
+    #[test]
+    fn test_config() {
+        let path = std::env::temp_dir().join(format!("gip-test-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+                [[providers]]
+                    name     = "example"
+                    ptype    = "IPv4"
+                    protocol = "HttpPlane"
+                    url      = "http://example.com"
+                    key      = []
+            "#,
+        )
+        .unwrap();
+        let args = ["gip", "-l", "--config", path.to_str().unwrap()];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_ok());
+
+        let args = ["gip", "-l", "--config", "/nonexistent/gip.toml"];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let args = ["gip", "config", "validate"];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        assert!(ret.is_ok());
+
+        let args = ["gip", "--url", "example.com", "config", "validate"];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_fmt_attempts() {
+        let attempts = [
+            Attempt {
+                name: String::from("ng"),
+                latency: Duration::from_millis(1000),
+                error: Some(String::from("failed to connect (http://example.com)")),
+            },
+            Attempt {
+                name: String::from("ident.me"),
+                latency: Duration::from_millis(52),
+                error: None,
+            },
+        ];
+        assert_eq!(
+            fmt_attempts(&attempts, false),
+            vec![
+                "    ng        FAIL  1000ms  failed to connect (http://example.com)",
+                "    ident.me  OK      52ms",
+            ]
+        );
+        assert_eq!(
+            fmt_attempts(&attempts[1..], true),
+            vec!["    ident.me  \x1b[32mOK\x1b[0m      52ms"]
+        );
+    }
+
     #[test]
     fn test_v6() {
-        let args = vec!["gip", "-6"];
-        let opt = Opt::from_iter(args.iter());
-        let _ = run_opt(&opt);
+        let server = std::net::TcpListener::bind("[::1]:0").unwrap();
+        let url = server.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for mut stream in server.incoming().flatten() {
+                let _ = std::io::Write::write_all(&mut stream, b"2001:db8::1\n");
+            }
+        });
+
+        let args = ["gip", "-6", "--url", &url, "--protocol", "tcp"];
+        let opt = parse(args.iter());
+        assert!(opt.v6);
+        assert!(run_opt(&opt).is_ok());
+
+        // IPv4 address is not accepted as IPv6
+        let url = tcp_server("192.0.2.1\n");
+        let args = ["gip", "-6", "--url", &url, "--protocol", "tcp"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_err());
     }
 
     #[test]
     fn test_proxy() {
-        let args = vec!["gip", "--proxy", "example.com:8080"];
-        let opt = Opt::from_iter(args.iter());
-        let _ = run_opt(&opt);
+        // the unresolvable host is reached only through the proxy
+        let (proxy, requests) = http_server("192.0.2.1\n");
+        let args = ["gip", "--proxy", &proxy, "--url", "http://gip.invalid/"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let args = [
+            "gip",
+            "--proxy",
+            "http://[invalid",
+            "--url",
+            "http://gip.invalid/",
+        ];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_err());
+    }
+
+    #[test]
+    fn test_consensus() {
+        let a = tcp_server("192.0.2.1\n");
+        let b = tcp_server("192.0.2.1\n");
+        let c = tcp_server("192.0.2.2\n");
+
+        let path = config_file(
+            "consensus",
+            &(tcp_provider("a", &a) + &tcp_provider("b", &b)),
+        );
+        let args = ["gip", "--consensus", "--config", path.to_str().unwrap()];
+        let opt = parse(args.iter());
+        assert!(opt.consensus);
+        let ret = run_opt(&opt);
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_ok());
+
+        let path = config_file(
+            "mismatch",
+            &(tcp_provider("a", &a) + &tcp_provider("c", &c)),
+        );
+        let args = ["gip", "--consensus", "--config", path.to_str().unwrap()];
+        let opt = parse(args.iter());
+        let err = run_opt(&opt).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(
+            err.chain().find_map(|x| x.downcast_ref::<gip::Error>()),
+            Some(gip::Error::AddressMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parallel() {
+        let args = ["gip", "--consensus", "--parallel", "2"];
+        let opt = parse(args.iter());
+        assert_eq!(opt.parallel, NonZeroUsize::new(2));
+
+        let args = ["gip", "--parallel", "0"];
+        assert!(Opt::from_iter_safe(args.iter()).is_err());
+
+        let mut client = ProviderAny::new();
+        assert_eq!(chunk_size(&client, 5), 5);
+        assert_eq!(chunk_size(&client, 0), 1);
+        client.parallel = NonZeroUsize::new(2);
+        assert_eq!(chunk_size(&client, 5), 2);
+    }
+
+    #[test]
+    fn test_geo() {
+        let (json, requests) = http_server(r#"{"ip": "192.0.2.1", "country": "JP"}"#);
+        let plane = tcp_server("192.0.2.1\n");
+        let providers = format!(
+            "{}[[providers]]\nname = \"json\"\nptype = \"IPv4\"\nprotocol = \"HttpJson\"\nurl = \"http://{}/\"\nkey = [\"ip\"]\ngeo = {{ country = [\"country\"] }}\n",
+            tcp_provider("plane", &plane),
+            json
+        );
+        let path = config_file("geo", &providers);
+
+        // only the provider with geolocation is used
+        let args = ["gip", "--geo", "-v", "--config", path.to_str().unwrap()];
+        let opt = parse(args.iter());
+        assert!(opt.geo);
+        let ret = run_opt(&opt);
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_fmt_error_json() {
+        let args = ["gip", "-j", "--url", "127.0.0.1:1", "--protocol", "tcp"];
+        let opt = parse(args.iter());
+        let err = run_opt(&opt).unwrap_err();
+        let json = fmt_error_json(&err);
+        assert_eq!(json["error"]["kind"], "all_providers_failed");
+        assert_eq!(json["error"]["category"], "network");
+        assert_eq!(json["error"]["providers"][0]["name"], "127.0.0.1:1");
+        assert_eq!(json["error"]["providers"][0]["kind"], "connection_failed");
+
+        let err = anyhow::anyhow!("provider not found: x");
+        assert_eq!(fmt_error_json(&err)["error"]["kind"], "other");
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let args = ["gip", "--url", "127.0.0.1:1", "--protocol", "tcp"];
+        let opt = parse(args.iter());
+        assert_eq!(exit_code(&run_opt(&opt).unwrap_err()), 2);
+
+        let args = ["gip", "--config", "/nonexistent/gip.toml"];
+        let opt = parse(args.iter());
+        assert_eq!(exit_code(&run_opt(&opt).unwrap_err()), 3);
+
+        // no provider of IPv6
+        let args = ["gip", "-6", "--provider", "ipinfo.io"];
+        let opt = parse(args.iter());
+        assert_eq!(exit_code(&run_opt(&opt).unwrap_err()), 4);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("failed")), 1);
+    }
+
+    #[test]
+    fn test_expect() {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = server.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for mut stream in server.incoming().take(2).flatten() {
+                let _ = std::io::Write::write_all(&mut stream, b"203.0.113.7\n");
+            }
+        });
+
+        let args = ["gip", "-q", "--url", &url, "--protocol", "tcp"];
+        let mut opt = parse(args.iter());
+        opt.expect = Some("203.0.113.7".parse().unwrap());
+        assert!(run_opt(&opt).is_ok());
+        opt.expect = Some("203.0.113.8".parse().unwrap());
+        assert_eq!(exit_code(&run_opt(&opt).unwrap_err()), 5);
+    }
+
+    #[test]
+    fn test_interface_addr() {
+        let addr = interface_addr("lo", ProviderInfoType::IPv4).unwrap();
+        assert_eq!(addr, IpAddr::from([127, 0, 0, 1]));
+        assert!(interface_addr("nonexistent0", ProviderInfoType::IPv4).is_err());
+    }
+
+    #[test]
+    fn test_leak() {
+        // reply the address of peer
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = server.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for mut stream in server.incoming().take(6).flatten() {
+                let peer = stream.peer_addr().unwrap().ip();
+                let _ = std::io::Write::write_all(&mut stream, format!("{}\n", peer).as_bytes());
+            }
+        });
+
+        let args = ["gip", "--url", &url, "--protocol", "tcp", "leak"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_err());
+
+        let mut opt = parse(args.iter());
+        opt.source_ip = Some("127.0.0.2".parse().unwrap());
+        assert!(run_opt(&opt).is_ok());
+        opt.source_ip = Some("127.0.0.1".parse().unwrap());
+        assert!(run_opt(&opt).is_err());
+
+        // the proxy of the provider is not used
+        let toml = tcp_provider("peer", &url) + "proxy = \"http://127.0.0.1:1\"\n";
+        let path = config_file("leak", &toml);
+        let args = ["gip", "--config", path.to_str().unwrap(), "leak"];
+        let mut opt = parse(args.iter());
+        opt.source_ip = Some("127.0.0.2".parse().unwrap());
+        let ret = run_opt(&opt);
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_ok());
+    }
+
+    #[test]
+    fn test_secure_only() {
+        let args = ["gip", "--secure-only", "--url", "http://127.0.0.1:1/"];
+        let opt = parse(args.iter());
+        let err = run_opt(&opt).unwrap_err();
+        assert!(err.to_string().contains("no secure provider"));
+        assert_eq!(exit_code(&err), 3);
+
+        let args = ["gip", "--secure-only", "-l"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+
+        // rows of `check` follow the providers kept by `secure_only`
+        let providers = "[[providers]]\nname = \"plain-a\"\nptype = \"IPv4\"\nprotocol = \"HttpPlane\"\nurl = \"http://127.0.0.1:1/a\"\nkey = []\n\
+             [[providers]]\nname = \"secure-b\"\nptype = \"IPv4\"\nprotocol = \"HttpPlane\"\nurl = \"https://127.0.0.1:1/b\"\nkey = []\n";
+        let path = config_file("secure-only", providers);
+        let args = [
+            "gip",
+            "--secure-only",
+            "--config",
+            path.to_str().unwrap(),
+            "check",
+        ];
+        let opt = parse(args.iter());
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let list = ProviderInfoList::from_toml(&text).unwrap();
+        let (client, indices) = select_providers(&opt, &list, false).unwrap();
+        assert_eq!(indices, vec![1]);
+        let rows = check_rows(&list, &indices, &check_all(&client));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][1], "secure-b");
+        assert_eq!(rows[0][2], "https://127.0.0.1:1/b");
+        assert_eq!(rows[0][3], "FAIL");
+        assert!(rows[0][5].contains("https://127.0.0.1:1/b"));
+    }
+
+    #[test]
+    fn test_only_protocol() {
+        let args = ["gip", "--only-protocol", "dns", "-l"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+        assert!(opt.only_protocol[0].matches(ProviderInfoProtocol::Dns));
+        assert!(!opt.only_protocol[0].matches(ProviderInfoProtocol::HttpPlane));
+
+        let args = ["gip", "--only-protocol", "http"];
+        let opt = parse(args.iter());
+        assert!(opt.only_protocol[0].matches(ProviderInfoProtocol::HttpJson));
+        assert!(!opt.only_protocol[0].matches(ProviderInfoProtocol::TcpPlane));
+
+        let args = ["gip", "--only-protocol", "ftp"];
+        assert!(Opt::from_iter_safe(args.iter()).is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let path = std::env::temp_dir().join(format!("gip-backoff-{}.json", std::process::id()));
+        let known: BTreeSet<String> = [String::from("limited")].into_iter().collect();
+        let client = ProviderAny::new();
+        save_backoff(&client, &path, &known);
+        assert!(!path.exists());
+
+        let until = std::time::SystemTime::now() + Duration::from_secs(60);
+        client.set_backoff("limited", until);
+        client.set_backoff("removed", until);
+        save_backoff(&client, &path, &known);
+
+        let restored = ProviderAny::new();
+        load_backoff(&restored, &path);
+        let backoff = restored.backoff();
+        assert_eq!(backoff.len(), 1);
+        assert_eq!(backoff[0].0, "limited");
+        assert!(backoff[0].1 >= until);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stats() {
+        let path = std::env::temp_dir().join(format!("gip-stats-{}.json", std::process::id()));
+        let known: BTreeSet<String> = [String::from("fast")].into_iter().collect();
+        let client = ProviderAny::new();
+        save_stats(&client, &path, &known);
+        assert!(!path.exists());
+
+        let stats = ProviderStats {
+            successes: 3,
+            failures: 1,
+            latency: Duration::from_millis(120),
+            success_latency: Some(Duration::from_millis(80)),
+        };
+        client.set_stats("fast", stats);
+        client.set_stats("removed", stats);
+        save_stats(&client, &path, &known);
+
+        let restored = ProviderAny::new();
+        load_stats(&restored, &path);
+        assert_eq!(restored.stats(), vec![(String::from("fast"), stats)]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_fmt_geo() {
+        let geo = GeoInfo {
+            country: Some(String::from("JP")),
+            region: None,
+            city: Some(String::from("Tokyo")),
+            organization: Some(String::from("AS2516 KDDI")),
+        };
+        assert_eq!(fmt_geo(&geo), "Tokyo, JP ( AS2516 KDDI )");
+    }
+
+    #[test]
+    fn test_bench_percentile() {
+        let result = BenchResult {
+            index: 0,
+            success: 4,
+            count: 5,
+            latencies: [10, 20, 30, 40].map(Duration::from_millis).to_vec(),
+        };
+        assert_eq!(result.success_rate(), 0.8);
+        assert_eq!(result.percentile(0), Some(Duration::from_millis(10)));
+        assert_eq!(result.percentile(50), Some(Duration::from_millis(20)));
+        assert_eq!(result.percentile(95), Some(Duration::from_millis(40)));
+    }
+
+    #[test]
+    fn test_reorder_providers() {
+        let text = "# my providers\n\
+                    [[providers]]\n    name = \"a\"\n\n\
+                    [[providers]]\n    name = \"b\"\n    [providers.headers]\n    X-Token = \"${TOKEN}\"\n\n\
+                    [[providers]]\n    name = \"c\"\n\n\
+                    [geoip]\n    asn = \"GeoLite2-ASN.mmdb\"\n";
+        let ret = reorder_providers(text, &[2, 0]).unwrap();
+        assert_eq!(
+            ret,
+            "# my providers\n\
+             [[providers]]\n    name = \"c\"\n\n\
+             [[providers]]\n    name = \"b\"\n    [providers.headers]\n    X-Token = \"${TOKEN}\"\n\n\
+             [[providers]]\n    name = \"a\"\n\n\
+             [geoip]\n    asn = \"GeoLite2-ASN.mmdb\"\n"
+        );
+        assert_eq!(reorder_providers(text, &[3]), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let args = ["gip", "check"];
+        let opt = parse(args.iter());
+        assert!(matches!(opt.cmd, Some(Command::Check)));
+
+        let url = tcp_server("192.0.2.1\n");
+        let args = ["gip", "--url", &url, "--protocol", "tcp", "check"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+
+        // any failed provider fails the check
+        let path = config_file(
+            "check",
+            &(tcp_provider("ok", &url) + &tcp_provider("ng", "127.0.0.1:1")),
+        );
+        let args = ["gip", "-j", "--config", path.to_str().unwrap(), "check"];
+        let opt = parse(args.iter());
+        let ret = run_opt(&opt);
+        let _ = std::fs::remove_file(&path);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_provider() {
+        let args = [
+            "gip",
+            "--provider",
+            "ident.me",
+            "--provider",
+            "tnedi.me",
+            "-l",
+        ];
+        let opt = parse(args.iter());
+        assert_eq!(opt.provider, vec!["ident.me", "tnedi.me"]);
+        assert!(run_opt(&opt).is_ok());
+
+        let args = ["gip", "--provider", "unknown", "-l"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_err());
+    }
+
+    #[test]
+    fn test_exclude() {
+        let args = [
+            "gip",
+            "--exclude",
+            "ident.me",
+            "--exclude",
+            "tnedi.me",
+            "-l",
+        ];
+        let opt = parse(args.iter());
+        assert_eq!(opt.exclude, vec!["ident.me", "tnedi.me"]);
+        assert!(run_opt(&opt).is_ok());
+    }
+
+    #[test]
+    fn test_url() {
+        let args = ["gip", "--url", "https://ifconfig.co/ip", "-l"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_ok());
+
+        let (addr, requests) = http_server(r#"{"ip": "192.0.2.1"}"#);
+        let url = format!("http://{}/json", addr);
+        let args = ["gip", "--url", &url, "--protocol", "json", "--key", "ip"];
+        let opt = parse(args.iter());
+        assert!(matches!(opt.protocol, Some(ProviderInfoProtocol::HttpJson)));
+        assert_eq!(opt.key, vec!["ip"]);
+        assert!(run_opt(&opt).is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let args = ["gip", "--url", &url, "--protocol", "json", "--key", "addr"];
+        let opt = parse(args.iter());
+        assert!(run_opt(&opt).is_err());
     }
 }
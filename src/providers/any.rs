@@ -0,0 +1,870 @@
+use crate::address::GlobalAddress;
+use crate::config::{Order, ProviderInfo, ProviderInfoList, ProviderInfoType, Settings, Strategy};
+use crate::error::{fmt_chain, Error};
+use crate::providers::{CancellationToken, Provider};
+use crate::DEFAULT_TOML;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// -------------------------------------------------------------------------------------------------
+// ProviderAny
+// -------------------------------------------------------------------------------------------------
+
+/// A provider tried by `ProviderAny::get_addr_with_attempts`
+#[derive(Clone, Debug)]
+pub struct Attempt {
+    /// Provider name
+    pub name: String,
+    /// Time until the provider succeeded or failed
+    pub latency: Duration,
+    /// Error message with the sources if the provider failed
+    pub error: Option<String>,
+}
+
+/// Success rate and latency of a provider recorded by `ProviderAny`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProviderStats {
+    /// Number of successful lookups
+    pub successes: u32,
+    /// Number of failed lookups
+    pub failures: u32,
+    /// Moving average of latency including failed lookups
+    pub latency: Duration,
+    /// Moving average of latency of successful lookups, which is reset by a failed lookup
+    pub success_latency: Option<Duration>,
+}
+
+impl ProviderStats {
+    /// Halve the counts beyond this number of lookups, so recent lookups dominate
+    pub(crate) const WINDOW: u32 = 32;
+
+    /// Record a lookup
+    pub fn record(&mut self, latency: Duration, success: bool) {
+        if self.successes + self.failures == 0 {
+            self.latency = latency;
+        } else {
+            self.latency = (self.latency * 7 + latency * 3) / 10;
+        }
+        if success {
+            self.successes += 1;
+            self.success_latency = Some(match self.success_latency {
+                Some(x) => (x * 7 + latency * 3) / 10,
+                None => latency,
+            });
+        } else {
+            self.failures += 1;
+            self.success_latency = None;
+        }
+        if self.successes + self.failures > Self::WINDOW {
+            self.successes /= 2;
+            self.failures /= 2;
+        }
+    }
+
+    /// Success rate, which is 0.5 without any lookup
+    pub fn success_rate(&self) -> f64 {
+        (self.successes as f64 + 1.0) / ((self.successes + self.failures) as f64 + 2.0)
+    }
+
+    /// Expected time until a successful lookup, which is zero without any lookup
+    fn cost(&self) -> f64 {
+        self.latency.as_secs_f64() / self.success_rate()
+    }
+}
+
+/// A `Provider` implementation to try multiple providers
+pub struct ProviderAny {
+    /// Providers for checking global address
+    pub providers: Vec<Box<dyn Provider>>,
+    /// Provider type
+    pub ptype: ProviderInfoType,
+    /// Strategy to get the address
+    pub strategy: Strategy,
+    /// Total deadline, which is checked before each provider is tried
+    pub deadline: Option<Duration>,
+    /// Order to try providers by `Strategy::Any`
+    pub order: Order,
+    /// Maximum number of providers queried concurrently by `get_addr_all`, and unlimited by `None`
+    pub parallel: Option<NonZeroUsize>,
+    /// Rate limited providers, which are skipped until the time
+    backoff: Mutex<BTreeMap<String, SystemTime>>,
+    /// Lookups recorded per provider
+    stats: Mutex<BTreeMap<String, ProviderStats>>,
+}
+
+impl ProviderAny {
+    pub fn new() -> Self {
+        ProviderAny {
+            providers: Vec::new(),
+            ptype: ProviderInfoType::IPv4,
+            strategy: Strategy::Any,
+            deadline: None,
+            order: Order::Random,
+            parallel: None,
+            backoff: Mutex::new(BTreeMap::new()),
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Create `ProviderAnyBuilder`
+    pub fn builder() -> ProviderAnyBuilder {
+        ProviderAnyBuilder::new()
+    }
+
+    /// Get rate limited providers by `ProviderInfo::id` and the time until they are skipped
+    pub fn backoff(&self) -> Vec<(String, SystemTime)> {
+        let now = SystemTime::now();
+        let mut backoff = self.backoff.lock().unwrap();
+        backoff.retain(|_, until| *until > now);
+        backoff.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    /// Skip the provider of `ProviderInfo::id` until the time ( e.g. restored from the previous run )
+    pub fn set_backoff(&self, key: &str, until: SystemTime) {
+        self.backoff
+            .lock()
+            .unwrap()
+            .insert(String::from(key), until);
+    }
+
+    /// Remaining time to skip the provider
+    fn remaining_backoff(&self, key: &str) -> Option<Duration> {
+        let backoff = self.backoff.lock().unwrap();
+        let until = backoff.get(key)?;
+        until.duration_since(SystemTime::now()).ok()
+    }
+
+    /// Record the rate limit of the provider
+    fn record_backoff(&self, key: &str, err: &Error) {
+        if let Error::RateLimited { retry_after, .. } = err {
+            self.set_backoff(key, SystemTime::now() + *retry_after);
+        }
+    }
+
+    /// Get the statistics of providers by `ProviderInfo::id` recorded by lookups
+    pub fn stats(&self) -> Vec<(String, ProviderStats)> {
+        let stats = self.stats.lock().unwrap();
+        stats.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    /// Set the statistics of the provider of `ProviderInfo::id` ( e.g. restored from the previous run )
+    pub fn set_stats(&self, key: &str, stats: ProviderStats) {
+        self.stats.lock().unwrap().insert(String::from(key), stats);
+    }
+
+    /// Record the lookup of the provider unless it is skipped or cancelled
+    fn record_stats(&self, key: &str, latency: Duration, ret: &Result<GlobalAddress, Error>) {
+        if matches!(ret, Err(Error::Backoff { .. } | Error::Cancelled)) {
+            return;
+        }
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(String::from(key)).or_default();
+        entry.record(latency, ret.is_ok());
+    }
+
+    /// Sort providers by `Order::Adaptive`, and providers with the same cost stay in random order
+    fn sort_adaptive(&self, providers: &mut [&dyn Provider]) {
+        let stats = self.stats.lock().unwrap();
+        let cost = |p: &dyn Provider| {
+            stats
+                .get(&provider_key(p))
+                .map(|x| x.cost())
+                .unwrap_or_default()
+        };
+        providers.sort_by(|a, b| cost(*a).total_cmp(&cost(*b)));
+    }
+
+    /// Sort providers by `Order::Fastest`
+    fn sort_fastest(&self, providers: &mut [&dyn Provider]) {
+        let stats = self.stats.lock().unwrap();
+        providers.sort_by_key(|p| match stats.get(&provider_key(*p)) {
+            Some(x) => x.success_latency.unwrap_or(Duration::MAX),
+            None => Duration::ZERO,
+        });
+    }
+
+    /// Load providers from TOML string
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let list = ProviderInfoList::from_toml(s)?;
+        let mut p = Vec::new();
+        for l in list.providers {
+            if l.enabled && !list.exclude.contains(&l.name) {
+                p.push(l.create());
+            }
+        }
+
+        let mut ret = ProviderAny {
+            providers: p,
+            ..ProviderAny::new()
+        };
+        ret.apply_settings(&list.settings);
+        check_secure_only(&ret, &[&list.settings])?;
+        Ok(ret)
+    }
+
+    /// Apply global settings to this and all providers
+    ///
+    /// `secure_only` removes the providers which send the query in cleartext,
+    /// and the custom providers without `ProviderInfo`.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        if let Some(timeout) = settings.timeout {
+            self.set_timeout(timeout);
+        }
+        if let Some(ref proxy) = settings.proxy {
+            self.set_proxy_url(proxy);
+        }
+        if let Some(ref user_agent) = settings.user_agent {
+            self.set_user_agent(user_agent);
+        }
+        if let Some(ptype) = settings.ptype {
+            self.ptype = ptype;
+        }
+        if let Some(strategy) = settings.strategy {
+            self.strategy = strategy;
+        }
+        if settings.deadline.is_some() {
+            self.deadline = settings.deadline;
+        }
+        if let Some(order) = settings.order {
+            self.order = order;
+        }
+        if settings.parallel.is_some() {
+            self.parallel = settings.parallel;
+        }
+        if settings.secure_only {
+            self.providers
+                .retain(|p| p.info().is_some_and(|x| x.is_secure()));
+        }
+    }
+
+    /// Retain only the providers specified by the predicate
+    ///
+    /// # Examples
+    /// ```
+    /// use gip::{Provider, ProviderAny, ProviderInfoProtocol};
+    /// let p = ProviderAny::from_toml(gip::DEFAULT_TOML)
+    ///     .unwrap()
+    ///     .retain(|p| p.get_protocol() == Some(ProviderInfoProtocol::Dns));
+    /// assert!(!p.providers.is_empty());
+    /// ```
+    pub fn retain<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&dyn Provider) -> bool,
+    {
+        self.providers.retain(|p| f(p.as_ref()));
+        self
+    }
+
+    /// Restrict providers to the ones whose name is in `names`
+    pub fn only(mut self, names: &[&str]) -> Self {
+        self.providers
+            .retain(|p| names.contains(&p.get_name().as_str()));
+        self
+    }
+
+    /// Remove providers whose name is in `names`
+    pub fn exclude(mut self, names: &[&str]) -> Self {
+        self.providers
+            .retain(|p| !names.contains(&p.get_name().as_str()));
+        self
+    }
+
+    /// Add a provider, e.g. a custom `Provider` implementation
+    ///
+    /// # Examples
+    /// ```
+    /// use gip::testing::MockProvider;
+    /// use gip::{Provider, ProviderAny, ProviderInfoType};
+    /// let mut p = ProviderAny::new();
+    /// p.add_provider(Box::new(
+    ///     MockProvider::new("internal", ProviderInfoType::IPv4).then_addr("192.0.2.1".parse().unwrap()),
+    /// ));
+    /// assert_eq!(p.get_addr().unwrap().v4addr, Some("192.0.2.1".parse().unwrap()));
+    /// assert!(p.remove_provider("internal"));
+    /// ```
+    pub fn add_provider(&mut self, provider: Box<dyn Provider>) {
+        self.providers.push(provider);
+    }
+
+    /// Remove providers named `name`, and return whether any provider is removed
+    pub fn remove_provider(&mut self, name: &str) -> bool {
+        let len = self.providers.len();
+        self.providers.retain(|p| p.get_name() != name);
+        self.providers.len() != len
+    }
+
+    /// Remove all providers
+    pub fn clear(&mut self) {
+        self.providers.clear();
+    }
+
+    /// Get global IP address by `Strategy::Any`, and return the providers tried in order
+    pub fn get_addr_with_attempts(
+        &self,
+        cancel: &CancellationToken,
+    ) -> (Result<GlobalAddress, Error>, Vec<Attempt>) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("any", ptype = ?self.ptype).entered();
+
+        let mut rng = thread_rng();
+        let mut providers: Vec<_> = self.providers.iter().map(|p| p.as_ref()).collect();
+        providers.shuffle(&mut rng);
+        match self.order {
+            Order::Random => (),
+            Order::Adaptive => self.sort_adaptive(&mut providers),
+            Order::Fastest => self.sort_fastest(&mut providers),
+        }
+
+        let start = Instant::now();
+        let mut attempts = Vec::new();
+        let mut errors = Vec::new();
+        for p in providers {
+            if p.get_type() == self.ptype {
+                if let Some(deadline) = self.deadline {
+                    if start.elapsed() >= deadline {
+                        return (Err(Error::DeadlineExceeded { deadline }), attempts);
+                    }
+                }
+                let name = p.get_name();
+                let key = provider_key(p);
+                if let Some(remaining) = self.remaining_backoff(&key) {
+                    let err = Error::Backoff { remaining };
+                    attempts.push(Attempt {
+                        name: name.clone(),
+                        latency: Duration::ZERO,
+                        error: Some(err.to_string()),
+                    });
+                    errors.push((name, err));
+                    continue;
+                }
+                let attempt_start = Instant::now();
+                let ret = p.get_addr_with_cancel(cancel);
+                let latency = attempt_start.elapsed();
+                self.record_stats(&key, latency, &ret);
+                match ret {
+                    Err(Error::Cancelled) => return (Err(Error::Cancelled), attempts),
+                    Ok(ret) => {
+                        attempts.push(Attempt {
+                            name: p.get_name(),
+                            latency,
+                            error: None,
+                        });
+                        return (Ok(ret), attempts);
+                    }
+                    Err(err) => {
+                        self.record_backoff(&key, &err);
+                        attempts.push(Attempt {
+                            name: name.clone(),
+                            latency,
+                            error: Some(fmt_chain(&err)),
+                        });
+                        errors.push((name, err));
+                    }
+                }
+            }
+        }
+        (Err(Error::AllProvidersFailed { errors }), attempts)
+    }
+
+    /// Query all providers of `ptype` concurrently, and return the results with the provider names
+    pub fn get_addr_all(&self) -> Vec<(String, Result<GlobalAddress, Error>)> {
+        let providers: Vec<_> = self
+            .providers
+            .iter()
+            .filter(|p| p.get_type() == self.ptype)
+            .collect();
+        parallel_map(&providers, self.parallel, |p| {
+            let name = p.get_name();
+            let key = provider_key(p.as_ref());
+            let (ret, latency) = match self.remaining_backoff(&key) {
+                Some(remaining) => (Err(Error::Backoff { remaining }), Duration::ZERO),
+                None => {
+                    let start = Instant::now();
+                    let ret = p.get_addr();
+                    (ret, start.elapsed())
+                }
+            };
+            self.record_stats(&key, latency, &ret);
+            if let Err(ref err) = ret {
+                self.record_backoff(&key, err);
+            }
+            (name, ret)
+        })
+    }
+
+    /// Query all providers of `ptype`, and fail with `Error::AddressMismatch` if they disagree
+    pub fn get_addr_consensus(&self) -> Result<GlobalAddress, Error> {
+        let mut addrs = Vec::new();
+        let mut errors = Vec::new();
+        for (name, ret) in self.get_addr_all() {
+            match ret {
+                Ok(addr) => addrs.push(addr),
+                Err(err) => errors.push((name, err)),
+            }
+        }
+
+        let answers: Vec<_> = addrs
+            .iter()
+            .filter_map(|x| x.addr().map(|addr| (x.provider.clone(), addr)))
+            .collect();
+        if answers.windows(2).any(|x| x[0].1 != x[1].1) {
+            return Err(Error::AddressMismatch { answers });
+        }
+
+        if addrs.is_empty() {
+            Err(Error::AllProvidersFailed { errors })
+        } else {
+            Ok(addrs.remove(0))
+        }
+    }
+}
+
+impl Default for ProviderAny {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply `f` to each item by at most `limit` threads, and return the results in order
+pub(crate) fn parallel_map<T, R, F>(items: &[T], limit: Option<NonZeroUsize>, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = limit.map_or(items.len(), |x| x.get().min(items.len()));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::from_iter((0..items.len()).map(|_| None)));
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let ret = f(item);
+                results.lock().unwrap()[i] = Some(ret);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.unwrap())
+        .collect()
+}
+
+/// Predicate given by `ProviderAnyBuilder::filter`
+type ProviderFilter = Box<dyn Fn(&ProviderInfo) -> bool>;
+
+/// Builder of `ProviderAny`
+///
+/// Settings given by the builder take precedence over `[settings]` table of TOML.
+///
+/// # Examples
+/// ```
+/// use gip::{ProviderAny, ProviderInfoProtocol, ProviderInfoType, Strategy};
+/// use std::time::Duration;
+/// let p = ProviderAny::builder()
+///     .providers_from_toml(gip::DEFAULT_TOML)
+///     .ptype(ProviderInfoType::IPv4)
+///     .timeout(Duration::from_millis(2000))
+///     .strategy(Strategy::Any)
+///     .filter(|info| info.protocol == ProviderInfoProtocol::Dns)
+///     .build()
+///     .unwrap();
+/// assert!(!p.providers.is_empty());
+/// ```
+#[derive(Default)]
+pub struct ProviderAnyBuilder {
+    providers: Vec<ProviderInfo>,
+    toml_settings: Vec<Settings>,
+    settings: Settings,
+    filters: Vec<ProviderFilter>,
+    error: Option<Error>,
+}
+
+impl ProviderAnyBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add providers and settings from TOML string, and the error is returned by `build`
+    pub fn providers_from_toml(mut self, s: &str) -> Self {
+        match ProviderInfoList::from_toml(s) {
+            Ok(list) => {
+                let exclude = list.exclude;
+                self.providers.extend(
+                    list.providers
+                        .into_iter()
+                        .filter(|x| !exclude.contains(&x.name)),
+                );
+                self.toml_settings.push(list.settings);
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    /// Add a provider
+    pub fn provider(mut self, info: ProviderInfo) -> Self {
+        self.providers.push(info);
+        self
+    }
+
+    /// Set the address type
+    pub fn ptype(mut self, ptype: ProviderInfoType) -> Self {
+        self.settings.ptype = Some(ptype);
+        self
+    }
+
+    /// Set timeout per each provider
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the strategy to get the address
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.settings.strategy = Some(strategy);
+        self
+    }
+
+    /// Set total deadline
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.settings.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the order to try providers
+    pub fn order(mut self, order: Order) -> Self {
+        self.settings.order = Some(order);
+        self
+    }
+
+    /// Set the maximum number of providers queried concurrently
+    pub fn parallel(mut self, parallel: NonZeroUsize) -> Self {
+        self.settings.parallel = Some(parallel);
+        self
+    }
+
+    /// Use only the providers which don't send the query in cleartext
+    pub fn secure_only(mut self, secure_only: bool) -> Self {
+        self.settings.secure_only = secure_only;
+        self
+    }
+
+    /// Use only the providers specified by the predicate, and multiple filters are combined by AND
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ProviderInfo) -> bool + 'static,
+    {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Create `ProviderAny`
+    pub fn build(self) -> Result<ProviderAny, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let filters = self.filters;
+        let mut ret = ProviderAny {
+            providers: self
+                .providers
+                .into_iter()
+                .filter(|info| info.enabled && filters.iter().all(|f| f(info)))
+                .map(|info| info.create())
+                .collect(),
+            ..ProviderAny::new()
+        };
+        for settings in &self.toml_settings {
+            ret.apply_settings(settings);
+        }
+        ret.apply_settings(&self.settings);
+        let settings: Vec<_> = self.toml_settings.iter().chain([&self.settings]).collect();
+        check_secure_only(&ret, &settings)?;
+        Ok(ret)
+    }
+}
+
+/// Fail if `secure_only` of the settings removed all providers
+fn check_secure_only(p: &ProviderAny, settings: &[&Settings]) -> Result<(), Error> {
+    if p.providers.is_empty() && settings.iter().any(|x| x.secure_only) {
+        return Err(Error::ConfigInvalid {
+            path: String::from("settings.secure_only"),
+            message: String::from(
+                "no secure provider is available ( only HTTPS providers are secure )",
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Key of stats and backoff, and the name is used for providers without `ProviderInfo`
+fn provider_key(p: &dyn Provider) -> String {
+    p.info().map(|x| x.id()).unwrap_or_else(|| p.get_name())
+}
+
+impl Provider for ProviderAny {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        self.get_addr_with_cancel(&CancellationToken::new())
+    }
+
+    fn get_addr_with_cancel(&self, cancel: &CancellationToken) -> Result<GlobalAddress, Error> {
+        if self.strategy == Strategy::Consensus {
+            return self.get_addr_consensus();
+        }
+        self.get_addr_with_attempts(cancel).0
+    }
+
+    fn get_name(&self) -> String {
+        String::from("any")
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.ptype
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        for p in &mut self.providers {
+            p.set_timeout(timeout)
+        }
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) {
+        for p in &mut self.providers {
+            p.set_connect_timeout(timeout)
+        }
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) {
+        for p in &mut self.providers {
+            p.set_read_timeout(timeout)
+        }
+    }
+
+    fn set_proxy_url(&mut self, url: &str) {
+        for p in &mut self.providers {
+            p.set_proxy_url(url)
+        }
+    }
+
+    fn set_user_agent(&mut self, user_agent: &str) {
+        for p in &mut self.providers {
+            p.set_user_agent(user_agent)
+        }
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        for p in &mut self.providers {
+            p.set_require_global(require_global)
+        }
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        for p in &mut self.providers {
+            p.set_local_address(addr)
+        }
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        for p in &mut self.providers {
+            p.set_capture_response(capture_response)
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderDefaultV4
+// -------------------------------------------------------------------------------------------------
+
+/// A convinient wrapper of `ProviderAny` with default providers for IPv4
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderDefaultV4};
+/// let p = ProviderDefaultV4::new();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderDefaultV4 {
+    provider: ProviderAny,
+}
+
+impl ProviderDefaultV4 {
+    pub fn new() -> Self {
+        ProviderDefaultV4 {
+            provider: ProviderAny::from_toml(DEFAULT_TOML).unwrap(),
+        }
+    }
+    /// Query all default providers concurrently, and return the results with the provider names
+    pub fn get_addr_all(&self) -> Vec<(String, Result<GlobalAddress, Error>)> {
+        self.provider.get_addr_all()
+    }
+    /// Query all default providers, and fail with `Error::AddressMismatch` if they disagree
+    pub fn get_addr_consensus(&self) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr_consensus()
+    }
+}
+
+impl Default for ProviderDefaultV4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderDefaultV4 {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr()
+    }
+
+    fn get_addr_with_cancel(&self, cancel: &CancellationToken) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr_with_cancel(cancel)
+    }
+
+    fn get_name(&self) -> String {
+        self.provider.get_name()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.provider.get_type()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.provider.set_timeout(timeout)
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.provider.set_connect_timeout(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) {
+        self.provider.set_read_timeout(timeout)
+    }
+
+    fn set_proxy_url(&mut self, url: &str) {
+        self.provider.set_proxy_url(url)
+    }
+
+    fn set_user_agent(&mut self, user_agent: &str) {
+        self.provider.set_user_agent(user_agent)
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        self.provider.set_require_global(require_global)
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        self.provider.set_local_address(addr)
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        self.provider.set_capture_response(capture_response)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderDefaultV6
+// -------------------------------------------------------------------------------------------------
+
+/// A convinient wrapper of `ProviderAny` with default providers for IPv6
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderDefaultV6};
+/// let p = ProviderDefaultV6::new();
+/// let addr = p.get_addr();
+/// match addr {
+///     Ok(x) => println!( "{:?}", x.v6addr ),
+///     Err(_) => (),
+/// }
+/// ```
+pub struct ProviderDefaultV6 {
+    provider: ProviderAny,
+}
+
+impl ProviderDefaultV6 {
+    pub fn new() -> Self {
+        let p = ProviderAny::builder()
+            .providers_from_toml(DEFAULT_TOML)
+            .ptype(ProviderInfoType::IPv6)
+            .build()
+            .unwrap();
+        ProviderDefaultV6 { provider: p }
+    }
+    /// Query all default providers concurrently, and return the results with the provider names
+    pub fn get_addr_all(&self) -> Vec<(String, Result<GlobalAddress, Error>)> {
+        self.provider.get_addr_all()
+    }
+    /// Query all default providers, and fail with `Error::AddressMismatch` if they disagree
+    pub fn get_addr_consensus(&self) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr_consensus()
+    }
+}
+
+impl Default for ProviderDefaultV6 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderDefaultV6 {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr()
+    }
+
+    fn get_addr_with_cancel(&self, cancel: &CancellationToken) -> Result<GlobalAddress, Error> {
+        self.provider.get_addr_with_cancel(cancel)
+    }
+
+    fn get_name(&self) -> String {
+        self.provider.get_name()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.provider.get_type()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.provider.set_timeout(timeout)
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.provider.set_connect_timeout(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) {
+        self.provider.set_read_timeout(timeout)
+    }
+
+    fn set_proxy_url(&mut self, url: &str) {
+        self.provider.set_proxy_url(url)
+    }
+
+    fn set_user_agent(&mut self, user_agent: &str) {
+        self.provider.set_user_agent(user_agent)
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        self.provider.set_require_global(require_global)
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        self.provider.set_local_address(addr)
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        self.provider.set_capture_response(capture_response)
+    }
+}
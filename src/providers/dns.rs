@@ -0,0 +1,478 @@
+use crate::address::GlobalAddress;
+use crate::config::{ProviderInfo, ProviderInfoDnsProtocol, ProviderInfoType};
+use crate::error::Error;
+use crate::providers::tcp::{proxy_connect, proxy_url, tcp_connect};
+use crate::providers::{attempt, log_response, parse_addr, Provider};
+use core::str::FromStr;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::proto::op::{Message, MessageType, OpCode, Query};
+use trust_dns_resolver::proto::rr::{DNSClass, Name, RData, RecordType};
+use trust_dns_resolver::Resolver;
+
+// -------------------------------------------------------------------------------------------------
+// ProviderDns
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address through DNS.
+/// `url` should be `[request domain name]@[resolver address]` or `[request domain name]@[resolver address]:[port]`.
+/// If resolver address is IP literal ( e.g. `193.108.88.1`, `[2001:db8::1]:53` ), it is used without lookup.
+/// Multiple resolvers can be separated by `,` ( e.g. `myip.opendns.com@resolver1.opendns.com,resolver2.opendns.com` ),
+/// and they are tried in order.
+/// `record` selects the record type and class ( e.g. "TXT", "CH TXT" ), and A/AAAA by `ptype` is used by default.
+/// `dnssec` validates the answer by DNSSEC with `dnssec` feature, and the answer of unsigned zone is rejected.
+/// Through SOCKS5 or HTTP proxy, the query is sent by TCP and the resolver is resolved by the proxy.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::Dns)
+///     .url("myip.opendns.com@resolver1.opendns.com")
+///     .create();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderDns {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderDns {
+    pub fn new() -> Self {
+        ProviderDns {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Get DNS class and record type from `record` ( e.g. "TXT", "CH TXT" )
+    pub(crate) fn dns_record(&self) -> Result<(DNSClass, RecordType), Error> {
+        parse_record(self.info.record.as_deref(), self.info.ptype).map_err(|_| {
+            Error::DnsRecordInvalid {
+                record: self.info.record.clone().unwrap_or_default(),
+            }
+        })
+    }
+
+    /// Lookup `req` through a resolver
+    fn lookup(
+        &self,
+        req: &str,
+        srv: &str,
+        class: DNSClass,
+        rtype: RecordType,
+        opts: ResolverOpts,
+        proxy: Option<&url::Url>,
+    ) -> Result<Vec<RData>, Error> {
+        let (srv, port) = split_host_port(srv).ok_or_else(|| Error::DnsParseFailed {
+            url: self.info.url.clone(),
+        })?;
+        let port = port.or(self.info.port).unwrap_or(53);
+
+        // UDP can't pass through proxy, and the resolver is resolved by the proxy
+        if let Some(proxy) = proxy {
+            let srv = if srv.contains(':') {
+                format!("[{}]:{}", srv, port)
+            } else {
+                format!("{}:{}", srv, port)
+            };
+            return dns_query_proxy(
+                proxy,
+                &srv,
+                self.info.local_address,
+                req,
+                class,
+                rtype,
+                opts.timeout,
+            );
+        }
+
+        // resolve resolver address unless it is IP literal
+        let srv = match IpAddr::from_str(srv) {
+            Ok(srv) => srv,
+            Err(_) => {
+                let config = bind_name_servers(ResolverConfig::default(), self.info.local_address);
+                let resolver = Resolver::new(config, opts)?;
+                match self.info.ptype {
+                    ProviderInfoType::IPv4 => {
+                        let srv = resolver.ipv4_lookup(srv)?;
+                        let srv = srv.iter().next().ok_or_else(|| Error::ConnectionFailed {
+                            url: self.info.url.clone(),
+                            source: None,
+                        })?;
+                        IpAddr::V4(**srv)
+                    }
+                    ProviderInfoType::IPv6 => {
+                        let srv = resolver.ipv6_lookup(srv)?;
+                        let srv = srv.iter().next().ok_or_else(|| Error::ConnectionFailed {
+                            url: self.info.url.clone(),
+                            source: None,
+                        })?;
+                        IpAddr::V6(**srv)
+                    }
+                }
+            }
+        };
+        let srv = SocketAddr::new(srv, port);
+
+        let lookup = |protocol| {
+            if class == DNSClass::IN {
+                let mut opts = opts;
+                opts.validate = self.info.dnssec;
+                let ns = NameServerConfig {
+                    socket_addr: srv,
+                    protocol,
+                    tls_dns_name: None,
+                    trust_negative_responses: false,
+                    bind_addr: self.info.local_address.map(|x| SocketAddr::new(x, 0)),
+                };
+                let mut config = ResolverConfig::new();
+                config.add_name_server(ns);
+                let resolver = Resolver::new(config, opts)?;
+                Ok(resolver.lookup(req, rtype)?.iter().cloned().collect())
+            } else {
+                dns_query(
+                    srv,
+                    self.info.local_address,
+                    req,
+                    class,
+                    rtype,
+                    protocol,
+                    opts.timeout,
+                )
+            }
+        };
+
+        match self.info.dns_protocol {
+            ProviderInfoDnsProtocol::Udp => lookup(Protocol::Udp),
+            ProviderInfoDnsProtocol::Tcp => lookup(Protocol::Tcp),
+            ProviderInfoDnsProtocol::UdpTcp => {
+                lookup(Protocol::Udp).or_else(|_| lookup(Protocol::Tcp))
+            }
+        }
+    }
+}
+
+/// Bind name servers of `config` to `local` address, and drop ones of the other family
+fn bind_name_servers(config: ResolverConfig, local: Option<IpAddr>) -> ResolverConfig {
+    let local = match local {
+        Some(x) => x,
+        None => return config,
+    };
+    let mut ret = ResolverConfig::new();
+    for ns in config.name_servers() {
+        if ns.socket_addr.is_ipv4() == local.is_ipv4() {
+            let mut ns = ns.clone();
+            ns.bind_addr = Some(SocketAddr::new(local, 0));
+            ret.add_name_server(ns);
+        }
+    }
+    ret
+}
+
+/// Split `host:port`, `[v6addr]:port` or `host` into host and optional port
+pub(crate) fn split_host_port(s: &str) -> Option<(&str, Option<u16>)> {
+    if let Some(rest) = s.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        match rest.strip_prefix(':') {
+            Some(port) => Some((host, Some(port.parse().ok()?))),
+            None if rest.is_empty() => Some((host, None)),
+            None => None,
+        }
+    } else if s.matches(':').count() == 1 {
+        let (host, port) = s.split_once(':')?;
+        Some((host, Some(port.parse().ok()?)))
+    } else {
+        Some((s, None))
+    }
+}
+
+/// Send DNS query of arbitrary class through UDP or TCP
+pub(crate) fn dns_query(
+    srv: SocketAddr,
+    local: Option<IpAddr>,
+    req: &str,
+    class: DNSClass,
+    rtype: RecordType,
+    protocol: Protocol,
+    timeout: Duration,
+) -> Result<Vec<RData>, Error> {
+    let msg = query_message(req, class, rtype)?;
+
+    let res = if protocol == Protocol::Tcp {
+        let mut stream = tcp_connect(&srv, local, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        tcp_exchange(&mut stream, &msg)?
+    } else {
+        let bind: SocketAddr = match local {
+            Some(local) => (local, 0).into(),
+            None if srv.is_ipv4() => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            None => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.send_to(&msg.to_vec()?, srv)?;
+
+        // packets from other senders are skipped, and don't extend the timeout
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0; 4096];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into());
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let (n, from) = socket.recv_from(&mut buf)?;
+            if from != srv {
+                continue;
+            }
+            match Message::from_vec(&buf[..n]) {
+                Ok(res) if res.id() == msg.id() => break res,
+                _ => continue,
+            }
+        }
+    };
+    Ok(answers(&res))
+}
+
+/// Send DNS query through TCP tunnel of `proxy` to `srv` ( `host:port` )
+pub(crate) fn dns_query_proxy(
+    proxy: &url::Url,
+    srv: &str,
+    local: Option<IpAddr>,
+    req: &str,
+    class: DNSClass,
+    rtype: RecordType,
+    timeout: Duration,
+) -> Result<Vec<RData>, Error> {
+    let msg = query_message(req, class, rtype)?;
+    let mut stream = proxy_connect(proxy, srv, local, timeout)?;
+    let res = tcp_exchange(&mut stream, &msg)?;
+    Ok(answers(&res))
+}
+
+fn query_message(req: &str, class: DNSClass, rtype: RecordType) -> Result<Message, Error> {
+    let mut query = Query::query(Name::from_ascii(req)?, rtype);
+    query.set_query_class(class);
+    let mut msg = Message::new();
+    msg.set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .add_query(query);
+    Ok(msg)
+}
+
+/// Send `msg` and receive the response through TCP stream
+fn tcp_exchange(stream: &mut TcpStream, msg: &Message) -> Result<Message, Error> {
+    // TCP message is prefixed by two byte length
+    let req = msg.to_vec()?;
+    let mut buf = (req.len() as u16).to_be_bytes().to_vec();
+    buf.extend_from_slice(&req);
+    stream.write_all(&buf)?;
+
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut buf)?;
+    Ok(Message::from_vec(&buf)?)
+}
+
+fn answers(res: &Message) -> Vec<RData> {
+    res.answers()
+        .iter()
+        .filter_map(|x| x.data().cloned())
+        .collect()
+}
+
+/// Parse `record` to DNS class and record type, and describe the problem if it is invalid
+///
+/// A/AAAA by `ptype` is used if `record` is not given.
+/// A/AAAA must match `ptype`, and CHAOS class can be used with TXT only.
+pub(crate) fn parse_record(
+    record: Option<&str>,
+    ptype: ProviderInfoType,
+) -> Result<(DNSClass, RecordType), String> {
+    let default = match ptype {
+        ProviderInfoType::IPv4 => RecordType::A,
+        ProviderInfoType::IPv6 => RecordType::AAAA,
+    };
+    let record = match record {
+        Some(x) => x,
+        None => return Ok((DNSClass::IN, default)),
+    };
+
+    let mut class = None;
+    let mut rtype = None;
+    for x in record.split_whitespace() {
+        let duplicated = match x.to_uppercase().as_str() {
+            "IN" => class.replace(DNSClass::IN).is_some(),
+            "CH" => class.replace(DNSClass::CH).is_some(),
+            "A" => rtype.replace(RecordType::A).is_some(),
+            "AAAA" => rtype.replace(RecordType::AAAA).is_some(),
+            "TXT" => rtype.replace(RecordType::TXT).is_some(),
+            _ => {
+                return Err(format!(
+                    "unknown record '{}', expected 'A', 'AAAA' or 'TXT' with optional class 'IN' or 'CH'",
+                    x
+                ))
+            }
+        };
+        if duplicated {
+            return Err(format!("class or type is specified twice in '{}'", record));
+        }
+    }
+
+    let class = class.unwrap_or(DNSClass::IN);
+    let rtype = rtype.unwrap_or(default);
+    match rtype {
+        RecordType::A | RecordType::AAAA if rtype != default => Err(format!(
+            "{} record can't give {:?} address, use {} or TXT",
+            rtype, ptype, default
+        )),
+        RecordType::A | RecordType::AAAA if class == DNSClass::CH => Err(format!(
+            "{} record is not supported in CH class, use TXT",
+            rtype
+        )),
+        _ => Ok((class, rtype)),
+    }
+}
+
+/// Check that DNSSEC validation is available for the provider
+///
+/// trust-dns ignores `ResolverOpts::validate` without its `dnssec` feature,
+/// so `dnssec = true` is rejected instead of returning unvalidated answers.
+pub(crate) fn check_dnssec(info: &ProviderInfo) -> Result<(), String> {
+    if !info.dnssec {
+        return Ok(());
+    }
+    if !cfg!(feature = "dnssec") {
+        return Err(String::from("gip is built without `dnssec` feature"));
+    }
+    match parse_record(info.record.as_deref(), info.ptype) {
+        Ok((DNSClass::IN, _)) => Ok(()),
+        _ => Err(String::from("DNSSEC is available for IN class only")),
+    }
+}
+
+/// Convert A/AAAA/TXT record to address string candidates
+pub(crate) fn rdata_to_strings(rdata: &RData) -> Vec<String> {
+    match rdata {
+        RData::A(x) => vec![x.to_string()],
+        RData::AAAA(x) => vec![x.to_string()],
+        RData::TXT(x) => x
+            .txt_data()
+            .iter()
+            .map(|x| String::from_utf8_lossy(x).trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Default for ProviderDns {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderDns {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let mut opts = ResolverOpts::default();
+            opts.timeout = self.timeout;
+
+            let (req, srvs) = if let Some(x) = self.info.url.find('@') {
+                let (req, srvs) = self.info.url.split_at(x);
+                (req, &srvs[1..])
+            } else {
+                return Err(Error::DnsParseFailed {
+                    url: self.info.url.clone(),
+                });
+            };
+
+            let (class, rtype) = self.dns_record()?;
+            let proxy = proxy_url(self.info.proxy.as_deref(), self.proxy.as_deref())?;
+            if self.info.dnssec {
+                let config_err = |message: String| Error::ConfigInvalid {
+                    path: self.info.name.clone(),
+                    message,
+                };
+                check_dnssec(&self.info).map_err(config_err)?;
+                if proxy.is_some() {
+                    return Err(config_err(String::from(
+                        "DNSSEC is not available through proxy",
+                    )));
+                }
+            }
+
+            // try resolvers in order
+            let mut ret = Err(Error::DnsParseFailed {
+                url: self.info.url.clone(),
+            });
+            for srv in srvs.split(',').map(|x| x.trim()) {
+                ret = self.lookup(req, srv, class, rtype, opts, proxy.as_ref());
+                if ret.is_ok() {
+                    break;
+                }
+            }
+            let rdata = ret?;
+
+            let addrs: Vec<String> = rdata.iter().flat_map(rdata_to_strings).collect();
+            log_response(&self.info, &addrs.join(", "));
+            let addr = addrs
+                .iter()
+                .find(|x| parse_addr(x, &self.info, start).is_ok())
+                .or_else(|| addrs.first())
+                .ok_or_else(|| Error::ConnectionFailed {
+                    url: self.info.url.clone(),
+                    source: None,
+                })?;
+            parse_addr(addr, &self.info, start)
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.info.ptype
+    }
+
+    fn info(&self) -> Option<&ProviderInfo> {
+        Some(&self.info)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout
+    }
+
+    fn set_proxy_url(&mut self, url: &str) {
+        self.proxy = Some(String::from(url))
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        self.info.require_global = require_global
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        self.info.local_address = Some(addr)
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        self.info.capture_response = capture_response
+    }
+}
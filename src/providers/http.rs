@@ -0,0 +1,987 @@
+use crate::address::{GeoInfo, GlobalAddress};
+use crate::config::{check_http3, ProviderInfo, ProviderInfoType};
+use crate::error::Error;
+use crate::providers::{attempt, log_response, parse_addr, Provider};
+use crate::{
+    DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_REDIRECTS, DEFAULT_RETRY_AFTER, DEFAULT_USER_AGENT,
+};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use regex::Regex;
+#[cfg(feature = "reqwest")]
+use reqwest::blocking::{Client, ClientBuilder, Response};
+use std::io::Read;
+#[cfg(not(feature = "reqwest"))]
+use std::net::ToSocketAddrs;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(feature = "reqwest")]
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------------------------
+// HTTP client
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+compile_error!("either `reqwest` or `ureq` feature is required");
+
+/// Address family of the connection to the provider, which is the same as the address to check.
+/// It is `None` through proxy because the connection to the proxy is not checked.
+pub(crate) fn transport_family(
+    info: &ProviderInfo,
+    proxy: Option<&str>,
+) -> Option<ProviderInfoType> {
+    match info.proxy.as_deref().or(proxy) {
+        Some("") => Some(info.ptype),
+        Some(_) => None,
+        // reqwest uses the proxy of environment variables
+        None if cfg!(feature = "reqwest")
+            && ["http_proxy", "https_proxy", "all_proxy"].iter().any(|x| {
+                std::env::var_os(x).is_some() || std::env::var_os(x.to_uppercase()).is_some()
+            }) =>
+        {
+            None
+        }
+        None => Some(info.ptype),
+    }
+}
+
+/// Whether the address is the family of `ptype`
+pub(crate) fn is_family(addr: &SocketAddr, ptype: ProviderInfoType) -> bool {
+    addr.is_ipv6() == (ptype == ProviderInfoType::IPv6)
+}
+
+#[cfg(feature = "reqwest")]
+pub(crate) fn http_client(
+    info: &ProviderInfo,
+    proxy: Option<&str>,
+    user_agent: &str,
+    family: Option<ProviderInfoType>,
+) -> Result<Client, Error> {
+    let err = |e: reqwest::Error| Error::ConnectionFailed {
+        url: info.url.clone(),
+        source: Some(e.into()),
+    };
+    check_http3(info).map_err(|message| Error::ConfigInvalid {
+        path: info.name.clone(),
+        message,
+    })?;
+    let mut builder = ClientBuilder::new();
+    // QUIC can't pass through proxy, so HTTP/3 is used only for direct access
+    #[cfg(feature = "http3")]
+    if info.http3 {
+        if transport_family(info, proxy).is_none() {
+            return Err(Error::ConfigInvalid {
+                path: info.name.clone(),
+                message: String::from("http3: HTTP/3 is not available through proxy"),
+            });
+        }
+        builder = builder.use_rustls_tls().http3_prior_knowledge();
+    }
+    match info.proxy.as_deref().or(proxy) {
+        Some("") => builder = builder.no_proxy(),
+        Some(proxy) => builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(err)?),
+        None => (),
+    }
+    if let Some(timeout) = info.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    // binding the unspecified address restricts the connection to the family
+    let unspecified = family.map(|x| match x {
+        ProviderInfoType::IPv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        ProviderInfoType::IPv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+    if let Some(addr) = info.local_address.or(unspecified) {
+        builder = builder.local_address(addr);
+    }
+    builder = builder.redirect(match max_redirects(info) {
+        0 => reqwest::redirect::Policy::none(),
+        x => reqwest::redirect::Policy::limited(x),
+    });
+    builder = builder.user_agent(info.user_agent.as_deref().unwrap_or(user_agent));
+    builder.build().map_err(err)
+}
+
+/// Maximum number of HTTP redirects, 0 means redirects are not followed
+fn max_redirects(info: &ProviderInfo) -> usize {
+    if info.follow_redirects == Some(false) {
+        0
+    } else {
+        info.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS)
+    }
+}
+
+/// HTTP client by ureq, which is used if `reqwest` feature is disabled
+#[cfg(not(feature = "reqwest"))]
+pub(crate) type Client = ureq::Agent;
+
+#[cfg(not(feature = "reqwest"))]
+pub(crate) fn http_client(
+    info: &ProviderInfo,
+    proxy: Option<&str>,
+    user_agent: &str,
+    family: Option<ProviderInfoType>,
+) -> Result<Client, Error> {
+    check_http3(info).map_err(|message| Error::ConfigInvalid {
+        path: info.name.clone(),
+        message,
+    })?;
+    if info.local_address.is_some() {
+        return Err(Error::ConnectionFailed {
+            url: info.url.clone(),
+            source: Some("local address is not supported by ureq".into()),
+        });
+    }
+    let mut builder = ureq::AgentBuilder::new();
+    match info.proxy.as_deref().or(proxy) {
+        Some("") | None => (),
+        Some(proxy) => {
+            // ureq resolves the target address through SOCKS5 proxy by `socks5`
+            let proxy = proxy.replacen("socks5h://", "socks5://", 1);
+            let proxy = ureq::Proxy::new(proxy).map_err(|e| Error::ConnectionFailed {
+                url: info.url.clone(),
+                source: Some(e.into()),
+            })?;
+            builder = builder.proxy(proxy);
+        }
+    }
+    if let Some(timeout) = info.connect_timeout {
+        builder = builder.timeout_connect(timeout);
+    }
+    if let Some(family) = family {
+        builder = builder.resolver(move |addr: &str| {
+            Ok(addr
+                .to_socket_addrs()?
+                .filter(|x| is_family(x, family))
+                .collect::<Vec<_>>())
+        });
+    }
+    builder = builder.redirects(max_redirects(info) as u32);
+    builder = builder.user_agent(info.user_agent.as_deref().unwrap_or(user_agent));
+    Ok(builder.build())
+}
+
+/// HTTP response by ureq, which has the same accessors as reqwest
+#[cfg(not(feature = "reqwest"))]
+pub(crate) struct Response {
+    status: http::StatusCode,
+    headers: HeaderMap,
+    reader: Box<dyn Read + Send + Sync>,
+}
+
+#[cfg(not(feature = "reqwest"))]
+impl Response {
+    pub(crate) fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+}
+
+#[cfg(not(feature = "reqwest"))]
+impl Read for Response {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+#[cfg(not(feature = "reqwest"))]
+impl From<ureq::Response> for Response {
+    fn from(res: ureq::Response) -> Self {
+        let mut headers = HeaderMap::new();
+        for name in res.headers_names() {
+            for value in res.all(&name) {
+                if let (Ok(name), Ok(value)) = (
+                    http::HeaderName::from_bytes(name.as_bytes()),
+                    http::HeaderValue::from_str(value),
+                ) {
+                    headers.append(name, value);
+                }
+            }
+        }
+        Response {
+            status: http::StatusCode::from_u16(res.status())
+                .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR),
+            headers,
+            reader: res.into_reader(),
+        }
+    }
+}
+
+/// Send request by ureq, and convert the error to `Error`
+///
+/// Error statuses ( 4xx, 5xx ) are returned as `Response` like reqwest.
+#[cfg(not(feature = "reqwest"))]
+fn ureq_send(
+    req: ureq::Request,
+    body: Option<&str>,
+    url: &str,
+    timeout: Duration,
+) -> Result<Response, Error> {
+    let ret = match body {
+        Some(body) => req.send_string(body),
+        None => req.call(),
+    };
+    match ret {
+        Ok(res) | Err(ureq::Error::Status(_, res)) => Ok(res.into()),
+        Err(ureq::Error::Transport(e)) => {
+            let timed_out = std::error::Error::source(&e)
+                .and_then(|x| x.downcast_ref::<std::io::Error>())
+                .is_some_and(|x| {
+                    matches!(
+                        x.kind(),
+                        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                    )
+                });
+            if timed_out {
+                Err(Error::Timeout {
+                    url: String::from(url),
+                    timeout,
+                })
+            } else {
+                Err(Error::ConnectionFailed {
+                    url: String::from(url),
+                    source: Some(e.into()),
+                })
+            }
+        }
+    }
+}
+
+/// Settings which require to rebuild HTTP client
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct HttpClientConfig {
+    proxy: Option<String>,
+    local_address: Option<IpAddr>,
+    family: Option<ProviderInfoType>,
+    max_redirects: usize,
+    http3: bool,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+}
+
+/// HTTP client which is reused while the settings are not changed
+#[derive(Default)]
+pub(crate) struct HttpClientCache {
+    pub(crate) inner: Mutex<Option<(HttpClientConfig, Client)>>,
+}
+
+impl HttpClientCache {
+    pub(crate) fn get(
+        &self,
+        info: &ProviderInfo,
+        proxy: Option<&str>,
+        user_agent: &str,
+    ) -> Result<Client, Error> {
+        let config = HttpClientConfig {
+            proxy: info.proxy.clone().or_else(|| proxy.map(String::from)),
+            local_address: info.local_address,
+            family: transport_family(info, proxy),
+            max_redirects: max_redirects(info),
+            http3: info.http3,
+            connect_timeout: info.connect_timeout,
+            user_agent: info
+                .user_agent
+                .clone()
+                .unwrap_or_else(|| String::from(user_agent)),
+        };
+        let mut inner = self.inner.lock().unwrap();
+        match *inner {
+            Some((ref x, ref client)) if *x == config => Ok(client.clone()),
+            _ => {
+                let client = http_client(info, proxy, user_agent, config.family)?;
+                *inner = Some((config, client.clone()));
+                Ok(client)
+            }
+        }
+    }
+}
+
+pub(crate) fn http_get(
+    client: &Client,
+    info: &ProviderInfo,
+    timeout: Duration,
+) -> Result<Response, Error> {
+    // no per-read timeout is available, so read timeout limits the whole request like `timeout`
+    let timeout = info.read_timeout.map_or(timeout, |x| x.min(timeout));
+
+    #[cfg(feature = "reqwest")]
+    let res = {
+        let mut req = client.get(&info.url).timeout(timeout);
+        #[cfg(feature = "http3")]
+        if info.http3 {
+            req = req.version(reqwest::Version::HTTP_3);
+        }
+        for (name, value) in &info.headers {
+            req = req.header(name, value);
+        }
+        if let Some(ref username) = info.username {
+            req = req.basic_auth(username, info.password.as_ref());
+        }
+        req.send()
+            .map_err(|e| reqwest_error(e, &info.url, timeout))?
+    };
+
+    #[cfg(not(feature = "reqwest"))]
+    let res = {
+        use base64::Engine;
+
+        let mut req = client.get(&info.url).timeout(timeout);
+        for (name, value) in &info.headers {
+            req = req.set(name, value);
+        }
+        if let Some(ref username) = info.username {
+            let credential = format!("{}:{}", username, info.password.as_deref().unwrap_or(""));
+            let credential = base64::engine::general_purpose::STANDARD.encode(credential);
+            req = req.set("Authorization", &format!("Basic {}", credential));
+        }
+        ureq_send(req, None, &info.url, timeout)?
+    };
+
+    // 503 is regarded as rate limit only if Retry-After is given
+    let retry_after = res
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .and_then(|x| x.to_str().ok())
+        .and_then(parse_retry_after);
+    match (res.status().as_u16(), retry_after) {
+        (429, x) | (503, x @ Some(_)) => {
+            return Err(Error::RateLimited {
+                url: info.url.clone(),
+                retry_after: x.unwrap_or(DEFAULT_RETRY_AFTER),
+            })
+        }
+        _ => (),
+    }
+
+    // redirect which is not followed ( e.g. to the login page of captive portal )
+    if res.status().is_redirection() {
+        let location = res
+            .headers()
+            .get(http::header::LOCATION)
+            .and_then(|x| x.to_str().ok())
+            .unwrap_or("");
+        return Err(Error::Redirected {
+            url: info.url.clone(),
+            location: String::from(location),
+        });
+    }
+    Ok(res)
+}
+
+/// Parse Retry-After header by seconds or HTTP-date
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+pub(crate) fn http_post(
+    client: &Client,
+    url: &str,
+    headers: &[(&str, String)],
+    body: String,
+    timeout: Duration,
+) -> Result<Response, Error> {
+    #[cfg(feature = "reqwest")]
+    {
+        let mut req = client.post(url).timeout(timeout);
+        for (name, value) in headers {
+            req = req.header(*name, value);
+        }
+        req.body(body)
+            .send()
+            .map_err(|e| reqwest_error(e, url, timeout))
+    }
+
+    #[cfg(not(feature = "reqwest"))]
+    {
+        let mut req = client.post(url).timeout(timeout);
+        for (name, value) in headers {
+            req = req.set(name, value);
+        }
+        ureq_send(req, Some(&body), url, timeout)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn reqwest_error(e: reqwest::Error, url: &str, timeout: Duration) -> Error {
+    if e.is_timeout() {
+        Error::Timeout {
+            url: String::from(url),
+            timeout,
+        }
+    } else {
+        Error::ConnectionFailed {
+            url: String::from(url),
+            source: Some(e.into()),
+        }
+    }
+}
+
+/// Whether the error while reading the body is caused by the timeout
+///
+/// reqwest wraps its own timeout error in `std::io::Error` with `ErrorKind::Other`.
+fn is_body_timeout(e: &std::io::Error) -> bool {
+    if matches!(
+        e.kind(),
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+    ) {
+        return true;
+    }
+    #[cfg(feature = "reqwest")]
+    if let Some(e) = e.get_ref().and_then(|x| x.downcast_ref::<reqwest::Error>()) {
+        return e.is_timeout();
+    }
+    false
+}
+
+pub(crate) fn read_body(
+    res: Response,
+    info: &ProviderInfo,
+    timeout: Duration,
+) -> Result<String, Error> {
+    // read one more byte to detect exceeding the limit
+    let limit = info.max_body_size.unwrap_or(DEFAULT_MAX_BODY_SIZE);
+    let mut body = Vec::new();
+    res.take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| {
+            if is_body_timeout(&e) {
+                Error::Timeout {
+                    url: info.url.clone(),
+                    timeout,
+                }
+            } else {
+                Error::Io(e)
+            }
+        })?;
+    if body.len() > limit {
+        return Err(Error::ResponseTooLarge {
+            url: info.url.clone(),
+            limit,
+        });
+    }
+    let body = String::from_utf8(body)
+        .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+    log_response(info, &body);
+    Ok(body)
+}
+
+/// Common `Provider` methods of HTTP providers
+macro_rules! impl_http_provider {
+    () => {
+        fn get_name(&self) -> String {
+            self.info.name.clone()
+        }
+
+        fn get_type(&self) -> ProviderInfoType {
+            self.info.ptype
+        }
+
+        fn info(&self) -> Option<&ProviderInfo> {
+            Some(&self.info)
+        }
+
+        fn set_timeout(&mut self, timeout: Duration) {
+            self.timeout = timeout
+        }
+
+        fn set_connect_timeout(&mut self, timeout: Duration) {
+            self.info.connect_timeout = Some(timeout)
+        }
+
+        fn set_read_timeout(&mut self, timeout: Duration) {
+            self.info.read_timeout = Some(timeout)
+        }
+
+        fn set_proxy_url(&mut self, url: &str) {
+            self.proxy = Some(String::from(url))
+        }
+
+        fn set_user_agent(&mut self, user_agent: &str) {
+            self.user_agent = String::from(user_agent)
+        }
+
+        fn set_require_global(&mut self, require_global: bool) {
+            self.info.require_global = require_global
+        }
+
+        fn set_local_address(&mut self, addr: IpAddr) {
+            self.info.local_address = Some(addr)
+        }
+
+        fn set_capture_response(&mut self, capture_response: bool) {
+            self.info.capture_response = capture_response
+        }
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderHttpPlane
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by plane text format.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo};
+/// let p = ProviderInfo::new()
+///     .url("http://v4.ipv6-test.com/api/myip.php")
+///     .create();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderHttpPlane {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderHttpPlane {
+    pub fn new() -> Self {
+        ProviderHttpPlane {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for ProviderHttpPlane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderHttpPlane {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let client = self
+                .client
+                .get(&self.info, self.proxy.as_deref(), &self.user_agent)?;
+            let res = http_get(&client, &self.info, self.timeout)?;
+            let body = read_body(res, &self.info, self.timeout)?;
+            parse_addr(body.trim(), &self.info, start)
+        })
+    }
+
+    impl_http_provider!();
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderHttpJson
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by JSON format.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::HttpJson)
+///     .url("http://ipv4.test-ipv6.com/ip/")
+///     .key(&[String::from("ip")])
+///     .padding("callback")
+///     .create();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderHttpJson {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderHttpJson {
+    pub fn new() -> Self {
+        ProviderHttpJson {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Parse response body as JSON with padding
+    fn parse_json(&self, body: &str) -> Result<serde_json::Value, Error> {
+        let body = match self.info.padding {
+            Some(ref padding) => {
+                let re = Regex::new(&format!(r"(?s){}\s*\((.*)\)", regex::escape(padding)))?;
+                let cap = re.captures(body).ok_or_else(|| Error::PaddingMismatch {
+                    padding: padding.clone(),
+                })?;
+                cap.get(1).map_or("", |x| x.as_str())
+            }
+            None => body,
+        };
+        Ok(serde_json::from_str(body)?)
+    }
+
+    /// Convert key path to JSON pointer
+    fn pointer(key: &[String]) -> String {
+        key.iter()
+            .map(|x| format!("/{}", x.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+
+    /// Extract address string from response body
+    pub(crate) fn parse_body(&self, body: &str) -> Result<String, Error> {
+        let json = self.parse_json(body)?;
+        let key = Self::pointer(&self.info.key);
+        let value = json
+            .pointer(&key)
+            .ok_or_else(|| Error::KeyNotFound { key: key.clone() })?;
+        let value = value.as_str().ok_or_else(|| Error::AddrParseFailed {
+            addr: value.to_string(),
+        })?;
+
+        // strip IP address
+        let re = Regex::new(r"([0-9a-zA-Z.:]+)")?;
+        let addr =
+            re.captures(value)
+                .and_then(|x| x.get(1))
+                .ok_or_else(|| Error::AddrParseFailed {
+                    addr: String::from(value),
+                })?;
+        Ok(String::from(addr.as_str()))
+    }
+
+    /// Extract geolocation information from response body
+    pub(crate) fn parse_geo(&self, body: &str) -> Option<GeoInfo> {
+        let keys = self.info.geo.as_ref()?;
+        let json = self.parse_json(body).ok()?;
+        let get = |key: &[String]| {
+            if key.is_empty() {
+                return None;
+            }
+            json.pointer(&Self::pointer(key)).and_then(|x| match x {
+                serde_json::Value::String(x) => Some(x.clone()),
+                serde_json::Value::Null => None,
+                x => Some(x.to_string()),
+            })
+        };
+        Some(GeoInfo {
+            country: get(&keys.country),
+            region: get(&keys.region),
+            city: get(&keys.city),
+            organization: get(&keys.organization),
+        })
+    }
+}
+
+impl Default for ProviderHttpJson {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderHttpJson {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let client = self
+                .client
+                .get(&self.info, self.proxy.as_deref(), &self.user_agent)?;
+            let res = http_get(&client, &self.info, self.timeout)?;
+            let body = read_body(res, &self.info, self.timeout)?;
+            let addr = self.parse_body(&body)?;
+            let mut ret = parse_addr(&addr, &self.info, start)?;
+            ret.geo = self.parse_geo(&body);
+            Ok(ret)
+        })
+    }
+
+    impl_http_provider!();
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderHttpRegex
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by regular expression.
+/// `regex` should have one capture group for the address.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::HttpRegex)
+///     .url("http://checkip.dyndns.org/")
+///     .regex(r"Current IP Address: ([0-9.]+)")
+///     .create();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderHttpRegex {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderHttpRegex {
+    pub fn new() -> Self {
+        ProviderHttpRegex {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Extract address string from response body
+    pub(crate) fn parse_body(&self, body: &str) -> Result<String, Error> {
+        let regex = self
+            .info
+            .regex
+            .as_ref()
+            .ok_or_else(|| Error::RegexNotSpecified {
+                url: self.info.url.clone(),
+            })?;
+        let re = Regex::new(regex)?;
+        let addr =
+            re.captures(body)
+                .and_then(|x| x.get(1))
+                .ok_or_else(|| Error::RegexMismatch {
+                    regex: regex.clone(),
+                })?;
+        Ok(String::from(addr.as_str().trim()))
+    }
+}
+
+impl Default for ProviderHttpRegex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderHttpRegex {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let client = self
+                .client
+                .get(&self.info, self.proxy.as_deref(), &self.user_agent)?;
+            let res = http_get(&client, &self.info, self.timeout)?;
+            let body = read_body(res, &self.info, self.timeout)?;
+            let addr = self.parse_body(&body)?;
+            parse_addr(&addr, &self.info, start)
+        })
+    }
+
+    impl_http_provider!();
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderHttpHeader
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by HTTP response header.
+/// The first element of `key` should be the header name.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::HttpHeader)
+///     .url("http://ip.example.com/")
+///     .key(&[String::from("X-Client-IP")])
+///     .create();
+/// let addr = p.get_addr();
+/// ```
+pub struct ProviderHttpHeader {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderHttpHeader {
+    pub fn new() -> Self {
+        ProviderHttpHeader {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Extract address string from response headers
+    pub(crate) fn parse_headers(&self, headers: &HeaderMap) -> Result<String, Error> {
+        let name = self
+            .info
+            .key
+            .first()
+            .ok_or_else(|| Error::KeyNotFound { key: String::new() })?;
+        let value = headers
+            .get(name.as_str())
+            .ok_or_else(|| Error::KeyNotFound { key: name.clone() })?;
+        let value = value.to_str().map_err(|_| Error::AddrParseFailed {
+            addr: format!("{:?}", value),
+        })?;
+
+        // the first address of list ( e.g. X-Forwarded-For )
+        let addr = value.split(',').next().unwrap_or("").trim();
+        Ok(String::from(addr))
+    }
+}
+
+impl Default for ProviderHttpHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderHttpHeader {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let client = self
+                .client
+                .get(&self.info, self.proxy.as_deref(), &self.user_agent)?;
+            let res = http_get(&client, &self.info, self.timeout)?;
+            let headers: Vec<_> = res
+                .headers()
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, String::from_utf8_lossy(v.as_bytes())))
+                .collect();
+            log_response(&self.info, &headers.join("\n"));
+            let addr = self.parse_headers(res.headers())?;
+            parse_addr(&addr, &self.info, start)
+        })
+    }
+
+    impl_http_provider!();
+}
+
+// -------------------------------------------------------------------------------------------------
+// ProviderHttpKeyValue
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by `key=value` lines.
+/// The first element of `key` should be the key of the address.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::HttpKeyValue)
+///     .url("https://1.1.1.1/cdn-cgi/trace")
+///     .key(&[String::from("ip")])
+///     .create();
+/// let addr = p.get_addr().unwrap();
+/// println!( "{:?}", addr.v4addr );
+/// ```
+pub struct ProviderHttpKeyValue {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderHttpKeyValue {
+    pub fn new() -> Self {
+        ProviderHttpKeyValue {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Extract address string from response body
+    pub(crate) fn parse_body(&self, body: &str) -> Result<String, Error> {
+        let key = self
+            .info
+            .key
+            .first()
+            .ok_or_else(|| Error::KeyNotFound { key: String::new() })?;
+        body.lines()
+            .filter_map(|x| x.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| String::from(v.trim()))
+            .ok_or_else(|| Error::KeyNotFound { key: key.clone() })
+    }
+}
+
+impl Default for ProviderHttpKeyValue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderHttpKeyValue {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let client = self
+                .client
+                .get(&self.info, self.proxy.as_deref(), &self.user_agent)?;
+            let res = http_get(&client, &self.info, self.timeout)?;
+            let body = read_body(res, &self.info, self.timeout)?;
+            let addr = self.parse_body(&body)?;
+            parse_addr(&addr, &self.info, start)
+        })
+    }
+
+    impl_http_provider!();
+}
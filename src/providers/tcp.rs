@@ -0,0 +1,370 @@
+use crate::address::GlobalAddress;
+use crate::config::{ProviderInfo, ProviderInfoType};
+use crate::error::Error;
+use crate::providers::http::is_family;
+use crate::providers::{attempt, log_response, parse_addr, Provider};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------------------------
+// ProviderTcpPlane
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking global address by plane text through raw TCP.
+/// `url` should be `[host]:[port]`.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::TcpPlane)
+///     .url("ip.example.com:4444")
+///     .create();
+/// let addr = p.get_addr();
+/// ```
+pub struct ProviderTcpPlane {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+impl ProviderTcpPlane {
+    pub fn new() -> Self {
+        ProviderTcpPlane {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            proxy: None,
+            last_query: Mutex::new(None),
+        }
+    }
+
+    fn connect(&self, addr: &str, timeout: Duration) -> std::io::Result<TcpStream> {
+        let proxy = proxy_url(self.info.proxy.as_deref(), self.proxy.as_deref())?;
+        if let Some(ref proxy) = proxy {
+            return proxy_connect(proxy, addr, self.info.local_address, timeout);
+        }
+
+        // connect by the family of the address to check unless through proxy
+        let target = addr
+            .to_socket_addrs()?
+            .find(|x| is_family(x, self.info.ptype))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("failed to resolve {} by {:?}", addr, self.info.ptype),
+                )
+            })?;
+
+        let stream = tcp_connect(&target, self.info.local_address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(stream)
+    }
+}
+
+impl Default for ProviderTcpPlane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderTcpPlane {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+            let addr = self.info.url.trim_start_matches("tcp://");
+
+            let connect_timeout = self.info.connect_timeout.unwrap_or(self.timeout);
+            let read_timeout = self.info.read_timeout.unwrap_or(self.timeout);
+            let timeout_err = |timeout: Duration| Error::Timeout {
+                url: self.info.url.clone(),
+                timeout,
+            };
+
+            let stream = self
+                .connect(addr, connect_timeout)
+                .map_err(|e| match e.kind() {
+                    std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                        timeout_err(connect_timeout)
+                    }
+                    _ => Error::ConnectionFailed {
+                        url: self.info.url.clone(),
+                        source: Some(e.into()),
+                    },
+                })?;
+            stream.set_read_timeout(Some(read_timeout))?;
+
+            // read until EOF or newline
+            let mut body = Vec::new();
+            let mut reader = stream.take(1024);
+            let mut buf = [0; 256];
+            loop {
+                let n = reader.read(&mut buf).map_err(|e| match e.kind() {
+                    std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                        timeout_err(read_timeout)
+                    }
+                    _ => Error::Io(e),
+                })?;
+                body.extend_from_slice(&buf[..n]);
+                if n == 0 || buf[..n].contains(&b'\n') {
+                    break;
+                }
+            }
+
+            let body = String::from_utf8_lossy(&body);
+            log_response(&self.info, &body);
+            let addr = body.lines().next().unwrap_or("").trim();
+            parse_addr(addr, &self.info, start)
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.info.ptype
+    }
+
+    fn info(&self) -> Option<&ProviderInfo> {
+        Some(&self.info)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.info.connect_timeout = Some(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) {
+        self.info.read_timeout = Some(timeout)
+    }
+
+    fn set_proxy_url(&mut self, url: &str) {
+        self.proxy = Some(String::from(url))
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        self.info.require_global = require_global
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        self.info.local_address = Some(addr)
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        self.info.capture_response = capture_response
+    }
+}
+
+/// Connect to `target` from `local` address if specified
+pub(crate) fn tcp_connect(
+    target: &SocketAddr,
+    local: Option<IpAddr>,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    match local {
+        Some(local) => {
+            let socket = socket2::Socket::new(
+                socket2::Domain::for_address(*target),
+                socket2::Type::STREAM,
+                Some(socket2::Protocol::TCP),
+            )?;
+            socket.bind(&SocketAddr::new(local, 0).into())?;
+            socket.connect_timeout(&(*target).into(), timeout)?;
+            Ok(socket.into())
+        }
+        None => TcpStream::connect_timeout(target, timeout),
+    }
+}
+
+/// Get proxy URL of the provider ( `info_proxy` overrides `proxy`, and empty string means direct )
+pub(crate) fn proxy_url(
+    info_proxy: Option<&str>,
+    proxy: Option<&str>,
+) -> std::io::Result<Option<url::Url>> {
+    match info_proxy.or(proxy) {
+        Some("") | None => Ok(None),
+        Some(x) => url::Url::parse(x)
+            .map(Some)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e)),
+    }
+}
+
+/// Open tunnel to `addr` ( `host:port` ) through SOCKS5 or HTTP proxy
+///
+/// Only `http`, `https`, `socks5` and `socks5h` proxies are accepted.
+///
+/// `addr` is resolved by the proxy.
+pub(crate) fn proxy_connect(
+    proxy: &url::Url,
+    addr: &str,
+    local: Option<IpAddr>,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    if !matches!(proxy.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unsupported proxy scheme: {}", proxy.scheme()),
+        ));
+    }
+
+    let target = format!(
+        "{}:{}",
+        proxy.host_str().unwrap_or(""),
+        proxy.port_or_known_default().unwrap_or(1080)
+    );
+    let target = target.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("failed to resolve {}", target),
+        )
+    })?;
+
+    let mut stream = tcp_connect(&target, local, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    match proxy.scheme() {
+        "socks5" | "socks5h" => socks5_connect(&mut stream, proxy, addr)?,
+        _ => http_connect(&mut stream, proxy, addr)?,
+    }
+    Ok(stream)
+}
+
+/// Username and password of the proxy URL, which are percent-decoded
+fn proxy_credential(proxy: &url::Url) -> Option<(String, String)> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+    let decode = |x: &str| {
+        percent_encoding::percent_decode_str(x)
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    Some((
+        decode(proxy.username()),
+        decode(proxy.password().unwrap_or("")),
+    ))
+}
+
+/// Open tunnel to `addr` through SOCKS5 proxy
+fn socks5_connect(stream: &mut TcpStream, proxy: &url::Url, addr: &str) -> std::io::Result<()> {
+    let err = |msg: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("socks5: {}", msg),
+        )
+    };
+
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| err("invalid address"))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port: u16 = port.parse().map_err(|_| err("invalid port"))?;
+
+    let credential = proxy_credential(proxy);
+    let auth = credential.is_some();
+    if auth {
+        stream.write_all(&[5, 2, 0, 2])?;
+    } else {
+        stream.write_all(&[5, 1, 0])?;
+    }
+    let mut buf = [0; 2];
+    stream.read_exact(&mut buf)?;
+    match buf {
+        [5, 0] => (),
+        [5, 2] if auth => {
+            let (username, password) = credential.unwrap_or_default();
+            let (username, password) = (username.as_bytes(), password.as_bytes());
+            if username.len() > 255 || password.len() > 255 {
+                return Err(err("username or password is too long"));
+            }
+            let mut req = vec![1, username.len() as u8];
+            req.extend_from_slice(username);
+            req.push(password.len() as u8);
+            req.extend_from_slice(password);
+            stream.write_all(&req)?;
+            stream.read_exact(&mut buf)?;
+            if buf[1] != 0 {
+                return Err(err("authentication failed"));
+            }
+        }
+        _ => return Err(err("unsupported authentication method")),
+    }
+
+    if host.len() > 255 {
+        return Err(err("host is too long"));
+    }
+    let mut req = vec![5, 1, 0, 3, host.len() as u8];
+    req.extend_from_slice(host.as_bytes());
+    req.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&req)?;
+
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf)?;
+    if buf[1] != 0 {
+        return Err(err("connection failed"));
+    }
+    let len = match buf[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(err("invalid reply")),
+    };
+    let mut bound = vec![0; len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(())
+}
+
+/// Maximum size of the response header from HTTP proxy
+const MAX_PROXY_HEADER: usize = 8192;
+
+/// Open tunnel to `addr` through HTTP proxy by CONNECT method
+fn http_connect(stream: &mut TcpStream, proxy: &url::Url, addr: &str) -> std::io::Result<()> {
+    use base64::Engine;
+
+    let mut req = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", addr, addr);
+    if let Some((username, password)) = proxy_credential(proxy) {
+        let credential = format!("{}:{}", username, password);
+        let credential = base64::engine::general_purpose::STANDARD.encode(credential);
+        req.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credential));
+    }
+    req.push_str("\r\n");
+    stream.write_all(req.as_bytes())?;
+
+    // the header is read by byte not to consume the tunneled data
+    let mut res = Vec::new();
+    let mut buf = [0; 1];
+    while !res.ends_with(b"\r\n\r\n") {
+        if res.len() >= MAX_PROXY_HEADER {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "proxy: response header is too large",
+            ));
+        }
+        stream.read_exact(&mut buf)?;
+        res.push(buf[0]);
+    }
+    let res = String::from_utf8_lossy(&res);
+    match res.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("proxy: {}", res.lines().next().unwrap_or("")),
+        )),
+    }
+}
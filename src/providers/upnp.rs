@@ -0,0 +1,219 @@
+use crate::address::GlobalAddress;
+use crate::config::{ProviderInfo, ProviderInfoType};
+use crate::error::Error;
+use crate::providers::http::{http_get, http_post, read_body, HttpClientCache};
+use crate::providers::{attempt, parse_addr, Provider};
+use crate::DEFAULT_USER_AGENT;
+use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------------------------
+// ProviderUpnp
+// -------------------------------------------------------------------------------------------------
+
+/// A `Provider` implementation for checking external address of the router through UPnP IGD.
+/// `url` is the device description URL of the router, and it is discovered by SSDP if empty.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol};
+/// let p = ProviderInfo::new()
+///     .protocol(ProviderInfoProtocol::Upnp)
+///     .create();
+/// let addr = p.get_addr();
+/// ```
+pub struct ProviderUpnp {
+    /// Provider info
+    pub info: ProviderInfo,
+    /// Timeout
+    pub timeout: Duration,
+    /// User-Agent
+    pub user_agent: String,
+    client: HttpClientCache,
+    /// Last query time checked by `min_interval`
+    last_query: Mutex<Option<Instant>>,
+}
+
+static SSDP_ADDR: &str = "239.255.255.250:1900";
+
+impl ProviderUpnp {
+    pub fn new() -> Self {
+        ProviderUpnp {
+            info: ProviderInfo::new(),
+            timeout: Duration::from_millis(1000),
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            client: HttpClientCache::default(),
+            last_query: Mutex::new(None),
+        }
+    }
+
+    /// Discover the device description URL of the router by SSDP
+    fn discover(&self) -> Result<String, Error> {
+        let bind: SocketAddr = match self.info.local_address {
+            Some(local) => (local, 0).into(),
+            None => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        let req = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n",
+            SSDP_ADDR
+        );
+        socket.send_to(req.as_bytes(), SSDP_ADDR)?;
+
+        let mut buf = [0; 2048];
+        loop {
+            let n = socket.recv(&mut buf).map_err(|e| match e.kind() {
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => Error::Timeout {
+                    url: String::from(SSDP_ADDR),
+                    timeout: self.timeout,
+                },
+                _ => Error::Io(e),
+            })?;
+            if let Some(location) = Self::parse_location(&String::from_utf8_lossy(&buf[..n])) {
+                return Ok(location);
+            }
+        }
+    }
+
+    /// Parse LOCATION header of SSDP response
+    pub(crate) fn parse_location(res: &str) -> Option<String> {
+        res.lines()
+            .filter_map(|x| x.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+            .map(|(_, value)| String::from(value.trim()))
+    }
+
+    /// Parse service type and control URL of WAN connection service from device description
+    pub(crate) fn parse_description(desc: &str, url: &str) -> Result<(String, String), Error> {
+        let re_service = Regex::new(r"(?s)<service>(.*?)</service>").unwrap();
+        let re_type = Regex::new(
+            r"<serviceType>\s*(urn:schemas-upnp-org:service:WAN(IP|PPP)Connection:\d)\s*</serviceType>",
+        )
+        .unwrap();
+        let re_control = Regex::new(r"<controlURL>\s*([^<]*?)\s*</controlURL>").unwrap();
+
+        for service in re_service.captures_iter(desc) {
+            let service = &service[1];
+            if let (Some(stype), Some(control)) =
+                (re_type.captures(service), re_control.captures(service))
+            {
+                let control = url::Url::parse(url)
+                    .and_then(|x| x.join(&control[1]))
+                    .map_err(|_| Error::UpnpServiceNotFound {
+                        url: String::from(url),
+                    })?;
+                return Ok((String::from(&stype[1]), control.to_string()));
+            }
+        }
+        Err(Error::UpnpServiceNotFound {
+            url: String::from(url),
+        })
+    }
+
+    /// Parse the address from the response of GetExternalIPAddress
+    pub(crate) fn parse_response(body: &str) -> Result<String, Error> {
+        let re =
+            Regex::new(r"<NewExternalIPAddress>\s*([^<\s]*)\s*</NewExternalIPAddress>").unwrap();
+        re.captures(body)
+            .map(|x| String::from(&x[1]))
+            .ok_or_else(|| Error::KeyNotFound {
+                key: String::from("NewExternalIPAddress"),
+            })
+    }
+}
+
+impl Default for ProviderUpnp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for ProviderUpnp {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        attempt(&self.info, &self.last_query, || {
+            let start = Instant::now();
+
+            let url = if self.info.url.is_empty() {
+                self.discover()?
+            } else {
+                self.info.url.clone()
+            };
+            let info = ProviderInfo {
+                url,
+                ..self.info.clone()
+            };
+
+            // the router is on the local network, so proxy is not used by default
+            let client = self.client.get(&info, Some(""), &self.user_agent)?;
+            let res = http_get(&client, &info, self.timeout)?;
+            let desc = read_body(res, &info, self.timeout)?;
+            let (service, control) = Self::parse_description(&desc, &info.url)?;
+
+            // read timeout limits the whole request as HTTP providers
+            let timeout = info
+                .read_timeout
+                .map_or(self.timeout, |x| x.min(self.timeout));
+            let body = format!(
+                "<?xml version=\"1.0\"?>\
+                 <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+                 <s:Body><u:GetExternalIPAddress xmlns:u=\"{}\"></u:GetExternalIPAddress></s:Body>\
+                 </s:Envelope>",
+                service
+            );
+            let headers = [
+                ("Content-Type", String::from("text/xml; charset=\"utf-8\"")),
+                (
+                    "SOAPAction",
+                    format!("\"{}#GetExternalIPAddress\"", service),
+                ),
+            ];
+            let res = http_post(&client, &control, &headers, body, timeout)?;
+            let body = read_body(res, &info, self.timeout)?;
+            let addr = Self::parse_response(&body)?;
+            parse_addr(&addr, &info, start)
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.info.ptype
+    }
+
+    fn info(&self) -> Option<&ProviderInfo> {
+        Some(&self.info)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.info.connect_timeout = Some(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) {
+        self.info.read_timeout = Some(timeout)
+    }
+
+    fn set_user_agent(&mut self, user_agent: &str) {
+        self.user_agent = String::from(user_agent)
+    }
+
+    fn set_require_global(&mut self, require_global: bool) {
+        self.info.require_global = require_global
+    }
+
+    fn set_local_address(&mut self, addr: IpAddr) {
+        self.info.local_address = Some(addr)
+    }
+
+    fn set_capture_response(&mut self, capture_response: bool) {
+        self.info.capture_response = capture_response
+    }
+}
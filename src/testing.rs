@@ -0,0 +1,289 @@
+//! Utilities to test code using `Provider` without network access
+
+use crate::address::GlobalAddress;
+use crate::config::ProviderInfoType;
+use crate::error::Error;
+use crate::providers::Provider;
+use std::collections::VecDeque;
+#[cfg(any(test, feature = "mock-server"))]
+use std::io::{Read, Write};
+use std::net::IpAddr;
+#[cfg(any(test, feature = "mock-server"))]
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(any(test, feature = "mock-server"))]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+#[cfg(any(test, feature = "mock-server"))]
+use trust_dns_resolver::proto::op::{Message, MessageType};
+#[cfg(any(test, feature = "mock-server"))]
+use trust_dns_resolver::proto::rr::RData;
+
+/// A `Provider` implementation returning scripted addresses and errors.
+///
+/// The responses are returned in order, and the last address is repeated.
+/// If no response remains, `Error::ConnectionFailed` is returned.
+///
+/// # Examples
+/// ```
+/// use gip::testing::MockProvider;
+/// use gip::{Error, Provider, ProviderInfoType};
+/// use std::time::Duration;
+/// let p = MockProvider::new("mock", ProviderInfoType::IPv4)
+///     .then_error(Error::Timeout { url: String::from("mock"), timeout: Duration::from_millis(100) })
+///     .then_addr("192.0.2.1".parse().unwrap());
+/// assert!(p.get_addr().is_err());
+/// assert_eq!(p.get_addr().unwrap().v4addr, Some("192.0.2.1".parse().unwrap()));
+/// assert_eq!(p.calls(), 2);
+/// ```
+pub struct MockProvider {
+    name: String,
+    ptype: ProviderInfoType,
+    latency: Duration,
+    responses: Mutex<VecDeque<Result<IpAddr, Error>>>,
+    calls: AtomicUsize,
+}
+
+impl MockProvider {
+    pub fn new(name: &str, ptype: ProviderInfoType) -> Self {
+        MockProvider {
+            name: String::from(name),
+            ptype,
+            latency: Duration::ZERO,
+            responses: Mutex::new(VecDeque::new()),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Add an address to the responses
+    pub fn then_addr(self, addr: IpAddr) -> Self {
+        self.responses.lock().unwrap().push_back(Ok(addr));
+        self
+    }
+
+    /// Add an error to the responses
+    pub fn then_error(self, err: Error) -> Self {
+        self.responses.lock().unwrap().push_back(Err(err));
+        self
+    }
+
+    /// Sleep before each response
+    pub fn latency(self, latency: Duration) -> Self {
+        MockProvider { latency, ..self }
+    }
+
+    /// Number of `get_addr` calls
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Provider for MockProvider {
+    fn get_addr(&self) -> Result<GlobalAddress, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(self.latency);
+
+        let ret = {
+            let mut responses = self.responses.lock().unwrap();
+            match responses.front() {
+                Some(Ok(addr)) if responses.len() == 1 => Some(Ok(*addr)),
+                _ => responses.pop_front(),
+            }
+        };
+        match ret {
+            Some(Ok(IpAddr::V4(addr))) => {
+                Ok(GlobalAddress::from_v4(addr, &self.name, self.latency))
+            }
+            Some(Ok(IpAddr::V6(addr))) => {
+                Ok(GlobalAddress::from_v6(addr, &self.name, self.latency))
+            }
+            Some(Err(err)) => Err(err),
+            None => Err(Error::ConnectionFailed {
+                url: format!("mock://{}", self.name),
+                source: Some("no scripted response".into()),
+            }),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_type(&self) -> ProviderInfoType {
+        self.ptype
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) {}
+}
+
+/// A local HTTP server returning the same response to every request.
+///
+/// It is available in the crate tests or with `mock-server` feature.
+///
+/// # Examples
+/// ```
+/// use gip::testing::MockHttpServer;
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol, ProviderInfoType};
+/// let server = MockHttpServer::new("192.0.2.1\n");
+/// let p = ProviderInfo::new()
+///     .ptype(ProviderInfoType::IPv4)
+///     .protocol(ProviderInfoProtocol::HttpPlane)
+///     .url(&server.url())
+///     .proxy("")
+///     .create();
+/// assert_eq!(p.get_addr().unwrap().v4addr, Some("192.0.2.1".parse().unwrap()));
+/// assert_eq!(server.requests(), 1);
+/// ```
+#[cfg(any(test, feature = "mock-server"))]
+pub struct MockHttpServer {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+#[cfg(any(test, feature = "mock-server"))]
+impl MockHttpServer {
+    /// Start a server returning `body` with status 200
+    pub fn new(body: &str) -> Self {
+        Self::with_headers(body, &[])
+    }
+
+    /// Start a server returning `body` and additional `headers` with status 200
+    pub fn with_headers(body: &str, headers: &[(&str, &str)]) -> Self {
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n",
+            body.len()
+        );
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        response.push_str(body);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let addr = listener.local_addr().expect("bind mock server");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut req = Vec::new();
+                let mut buf = [0; 1024];
+                while !req.windows(4).any(|x| x == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => req.extend_from_slice(&buf[..n]),
+                    }
+                }
+                count.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        MockHttpServer { addr, requests }
+    }
+
+    /// Server address
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// URL of the server root
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Number of received requests
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// A local DNS server answering every UDP query with the same record.
+///
+/// It is available in the crate tests or with `mock-server` feature.
+///
+/// # Examples
+/// ```
+/// use gip::testing::MockDnsServer;
+/// use gip::{Provider, ProviderInfo, ProviderInfoProtocol, ProviderInfoType};
+/// let server = MockDnsServer::txt("192.0.2.1");
+/// let p = ProviderInfo::new()
+///     .ptype(ProviderInfoType::IPv4)
+///     .protocol(ProviderInfoProtocol::Dns)
+///     .url(&server.url("o-o.myaddr.l.google.com"))
+///     .record("TXT")
+///     .create();
+/// assert_eq!(p.get_addr().unwrap().v4addr, Some("192.0.2.1".parse().unwrap()));
+/// ```
+#[cfg(any(test, feature = "mock-server"))]
+pub struct MockDnsServer {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+#[cfg(any(test, feature = "mock-server"))]
+impl MockDnsServer {
+    /// Start a server answering A or AAAA record of `addr`
+    pub fn new(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(x) => Self::start(RData::A(x.into())),
+            IpAddr::V6(x) => Self::start(RData::AAAA(x.into())),
+        }
+    }
+
+    /// Start a server answering TXT record of `text` in the class of the query
+    pub fn txt(text: &str) -> Self {
+        use trust_dns_resolver::proto::rr::rdata::TXT;
+        Self::start(RData::TXT(TXT::new(vec![String::from(text)])))
+    }
+
+    fn start(rdata: RData) -> Self {
+        use trust_dns_resolver::proto::rr::Record;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("bind mock server");
+        let addr = socket.local_addr().expect("bind mock server");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count = requests.clone();
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok((n, from)) = socket.recv_from(&mut buf) {
+                let Ok(req) = Message::from_vec(&buf[..n]) else {
+                    continue;
+                };
+                let Some(query) = req.queries().first().cloned() else {
+                    continue;
+                };
+                count.fetch_add(1, Ordering::SeqCst);
+                let mut record = Record::from_rdata(query.name().clone(), 0, rdata.clone());
+                record.set_dns_class(query.query_class());
+                let mut res = Message::new();
+                res.set_id(req.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_desired(req.recursion_desired())
+                    .set_recursion_available(true)
+                    .add_query(query)
+                    .add_answer(record);
+                if let Ok(res) = res.to_vec() {
+                    let _ = socket.send_to(&res, from);
+                }
+            }
+        });
+        MockDnsServer { addr, requests }
+    }
+
+    /// Server address
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Provider URL querying `name` to the server
+    pub fn url(&self, name: &str) -> String {
+        format!("{}@{}", name, self.addr)
+    }
+
+    /// Number of received queries
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
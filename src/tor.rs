@@ -0,0 +1,62 @@
+use crate::config::ProviderInfo;
+use crate::error::Error;
+use crate::providers::http::{http_client, http_get, read_body};
+use crate::DEFAULT_USER_AGENT;
+use core::str::FromStr;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+// -------------------------------------------------------------------------------------------------
+// Tor
+// -------------------------------------------------------------------------------------------------
+
+/// Check endpoint of the Tor Project
+pub static TOR_CHECK_URL: &str = "https://check.torproject.org/api/ip";
+
+/// Default SOCKS proxy of Tor
+pub static TOR_PROXY: &str = "socks5h://127.0.0.1:9050";
+
+/// Result of `check_tor`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TorCheck {
+    /// Whether the egress node is a known Tor exit
+    pub is_tor: bool,
+    /// Address seen by the check endpoint
+    pub addr: IpAddr,
+}
+
+/// Check whether the egress through `proxy` is a known Tor exit by the Tor Project's endpoint.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{check_tor, TOR_PROXY};
+/// use std::time::Duration;
+/// if let Ok(x) = check_tor(TOR_PROXY, Duration::from_millis(10000)) {
+///     println!("{}: {}", x.addr, x.is_tor);
+/// }
+/// ```
+pub fn check_tor(proxy: &str, timeout: Duration) -> Result<TorCheck, Error> {
+    check_tor_url(TOR_CHECK_URL, proxy, timeout)
+}
+
+pub(crate) fn check_tor_url(url: &str, proxy: &str, timeout: Duration) -> Result<TorCheck, Error> {
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(rename = "IsTor")]
+        is_tor: bool,
+        #[serde(rename = "IP")]
+        ip: String,
+    }
+
+    let info = ProviderInfo::new().name("check.torproject.org").url(url);
+    let client = http_client(&info, Some(proxy), DEFAULT_USER_AGENT, None)?;
+    let res = http_get(&client, &info, timeout)?;
+    let body = read_body(res, &info, timeout)?;
+    let res: Response = serde_json::from_str(&body)?;
+    let addr = IpAddr::from_str(&res.ip).map_err(|_| Error::AddrParseFailed { addr: res.ip })?;
+    Ok(TorCheck {
+        is_tor: res.is_tor,
+        addr,
+    })
+}
@@ -0,0 +1,220 @@
+use crate::address::GlobalAddress;
+#[cfg(feature = "async")]
+use crate::providers::any::ProviderAny;
+use crate::providers::{CancellationToken, Provider};
+use std::net::IpAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------------------------
+// Watch
+// -------------------------------------------------------------------------------------------------
+
+/// Change of the global address detected by polling
+#[derive(Debug)]
+pub struct AddressChange {
+    /// Previous address, which is `None` at the first detection
+    pub old: Option<IpAddr>,
+    /// Current address
+    pub new: GlobalAddress,
+}
+
+/// Poll the provider by `interval`, and call `notify` at each change until it returns false or
+/// `cancel` is cancelled.
+///
+/// Failed lookups are retried by the doubled interval, up to 8 times the interval.
+pub(crate) fn poll_changes<P, F>(
+    provider: &P,
+    interval: Duration,
+    cancel: &CancellationToken,
+    mut notify: F,
+) where
+    P: Provider + ?Sized,
+    F: FnMut(AddressChange) -> bool,
+{
+    let mut last = None;
+    let mut failures = 0;
+    while !cancel.is_cancelled() {
+        let wait = match provider.get_addr_with_cancel(cancel) {
+            Ok(addr) => {
+                failures = 0;
+                if addr.addr() != last {
+                    let old = std::mem::replace(&mut last, addr.addr());
+                    if !notify(AddressChange { old, new: addr }) {
+                        return;
+                    }
+                }
+                interval
+            }
+            Err(_) => {
+                failures = (failures + 1).min(3);
+                interval * 2u32.pow(failures)
+            }
+        };
+        sleep_with_cancel(wait, cancel);
+    }
+}
+
+/// Sleep until the duration elapses or `cancel` is cancelled
+fn sleep_with_cancel(duration: Duration, cancel: &CancellationToken) {
+    let end = Instant::now() + duration;
+    while !cancel.is_cancelled() {
+        let remaining = end.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        thread::sleep(remaining.min(Duration::from_millis(100)));
+    }
+}
+
+/// Callback given by `Monitor::on_change`
+type ChangeCallback = Box<dyn FnMut(Option<IpAddr>, &GlobalAddress) + Send>;
+
+/// Poll the address by a provider on a background thread, and call callbacks at each change
+///
+/// The first detected address is notified with `None` as the previous address,
+/// and failed lookups are retried by the doubled interval.
+///
+/// # Examples
+/// ```no_run
+/// use gip::{Monitor, ProviderDefaultV4};
+/// use std::time::Duration;
+/// let handle = Monitor::new(ProviderDefaultV4::new(), Duration::from_secs(60))
+///     .on_change(|old, new| println!("{:?} -> {:?}", old, new.addr()))
+///     .start();
+/// // ...
+/// handle.stop();
+/// ```
+pub struct Monitor {
+    provider: Box<dyn Provider>,
+    interval: Duration,
+    callbacks: Vec<ChangeCallback>,
+}
+
+impl Monitor {
+    pub fn new<P: Provider + 'static>(provider: P, interval: Duration) -> Self {
+        Monitor {
+            provider: Box::new(provider),
+            interval,
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Add a callback called by the previous and the current address at each change
+    pub fn on_change<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(Option<IpAddr>, &GlobalAddress) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(f));
+        self
+    }
+
+    /// Start polling on a background thread
+    pub fn start(self) -> MonitorHandle {
+        let Monitor {
+            provider,
+            interval,
+            mut callbacks,
+        } = self;
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let thread = thread::spawn(move || {
+            poll_changes(provider.as_ref(), interval, &token, |change| {
+                for f in &mut callbacks {
+                    f(change.old, &change.new);
+                }
+                true
+            })
+        });
+        MonitorHandle {
+            cancel,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Handle of a running `Monitor`, which stops the monitor when dropped
+pub struct MonitorHandle {
+    cancel: CancellationToken,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MonitorHandle {
+    /// Stop the monitor, and wait until the lookup in flight is finished
+    pub fn stop(mut self) {
+        self.cancel.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Check whether the monitor is running
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|x| !x.is_finished())
+    }
+}
+
+impl Drop for MonitorHandle {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+#[cfg(feature = "async")]
+impl ProviderAny {
+    /// Poll the address by `interval` on a background thread, and yield an event at each change
+    ///
+    /// The first detected address is yielded with `old: None`, and failed lookups are retried
+    /// by the doubled interval. The thread stops when the stream is dropped.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use futures_executor::block_on_stream;
+    /// use gip::ProviderAny;
+    /// use std::time::Duration;
+    /// let p = ProviderAny::from_toml(gip::DEFAULT_TOML).unwrap();
+    /// for change in block_on_stream(p.watch(Duration::from_secs(60))) {
+    ///     println!("{:?} -> {:?}", change.old, change.new.addr());
+    /// }
+    /// ```
+    pub fn watch(
+        self,
+        interval: Duration,
+    ) -> impl futures_core::Stream<Item = AddressChange> + Send + Unpin {
+        let (tx, rx) = futures_channel::mpsc::unbounded();
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        thread::spawn(move || {
+            poll_changes(&self, interval, &token, |change| {
+                tx.unbounded_send(change).is_ok()
+            })
+        });
+        AddressChanges { rx, cancel }
+    }
+}
+
+/// Stream returned by `ProviderAny::watch`
+#[cfg(feature = "async")]
+struct AddressChanges {
+    rx: futures_channel::mpsc::UnboundedReceiver<AddressChange>,
+    cancel: CancellationToken,
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for AddressChanges {
+    type Item = AddressChange;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<AddressChange>> {
+        std::pin::Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for AddressChanges {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}